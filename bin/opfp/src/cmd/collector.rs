@@ -0,0 +1,542 @@
+//! Native collection of the witness data of a fault proof fixture.
+
+use alloy_primitives::{keccak256, uint, Address, Bytes, B256, U256};
+use alloy_provider::{Provider, ReqwestProvider};
+use color_eyre::{eyre::eyre, Result};
+use fp_test_fixtures::{FaultProofInputs, PreimageKey, PreimageKeyType};
+use reqwest::Url;
+use serde::Deserialize;
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use tracing::{debug, info};
+
+use super::util::OutputResponse;
+use super::witness::{
+    keccak_key, ordered_trie, rlp_items, rlp_string, BlockHeader, OUTPUT_ROOT_PREIMAGE_SIZE,
+};
+
+/// The logging target to use for [tracing].
+const TARGET: &str = "native-collector";
+
+/// The type of EIP-4844 blob transactions.
+const BLOB_TX_TYPE: u8 = 3;
+
+/// The version byte of the versioned hash of a KZG commitment.
+const BLOB_COMMITMENT_VERSION_KZG: u8 = 1;
+
+/// The number of 32 byte field elements in a blob.
+const FIELD_ELEMENTS_PER_BLOB: usize = 4096;
+
+/// The modulus of the BLS12-381 scalar field.
+const BLS_MODULUS: U256 =
+    uint!(0x73eda753299d7d483339d80809a1d80553bda402fffe5bfeffffffff00000001_U256);
+
+/// The generator of the multiplicative group of the BLS12-381 scalar field.
+const PRIMITIVE_ROOT_OF_UNITY: U256 = uint!(7_U256);
+
+/// Collects the witness data of a fixture directly from the L1 and L2 execution clients,
+/// without running the op-program host.
+///
+/// The collector records the preimages which the fault proof program fetches for derivation
+/// and execution: the L1 headers, transactions and receipts from the L1 head back to the
+/// lookback window before the L1 origin of the agreed L2 block, the blobs of the blob
+/// transactions sent to the batch inbox in those L1 blocks, the agreed L2 output root
+/// preimage, the agreed L2 block and its transactions, and the execution witness of every
+/// L2 block being validated. Precompile preimages are not collected, so the collected witness
+/// data must be verified by running it before it is used.
+///
+/// Both execution clients must serve the `debug_getRawBlock` and `debug_getRawReceipts`
+/// methods, and the L2 client must serve `debug_executionWitness`.
+#[derive(Debug)]
+pub struct NativeCollector {
+    /// The L1 execution client.
+    l1: ReqwestProvider,
+    /// The L2 execution client.
+    l2: ReqwestProvider,
+    /// The L1 beacon client, serving the blob sidecars.
+    beacon: BeaconClient,
+    /// The witness data collected so far.
+    witness_data: BTreeMap<B256, Bytes>,
+}
+
+impl NativeCollector {
+    /// Creates a new [NativeCollector] from the L1 and L2 execution client URLs and the L1
+    /// beacon client URL.
+    pub fn new_http(l1_url: Url, l2_url: Url, beacon_url: Url) -> Self {
+        Self {
+            l1: ReqwestProvider::new_http(l1_url),
+            l2: ReqwestProvider::new_http(l2_url),
+            beacon: BeaconClient::new(beacon_url),
+            witness_data: BTreeMap::new(),
+        }
+    }
+
+    /// Collects the witness data needed to validate the L2 blocks after the agreed output up
+    /// to the L2 block of the given inputs. L1 blocks are collected from the L1 head back to
+    /// `l1_lookback` blocks before the L1 origin of the agreed L2 block, along with the blobs
+    /// sent to the given batch inbox.
+    pub async fn collect(
+        mut self,
+        inputs: &FaultProofInputs,
+        agreed_output: &OutputResponse,
+        l1_lookback: u64,
+        batch_inbox: Address,
+    ) -> Result<BTreeMap<B256, Bytes>> {
+        self.collect_output_root(agreed_output)?;
+
+        let l1_start = agreed_output
+            .block_ref
+            .l1_origin
+            .number
+            .saturating_sub(l1_lookback);
+        let mut l1_hash = inputs.l1_head;
+        loop {
+            let (header, transactions) = self.collect_block(Chain::L1, l1_hash).await?;
+            let blob_hashes = batch_blob_hashes(&transactions, batch_inbox)?;
+            if !blob_hashes.is_empty() {
+                self.collect_blobs(&header, &blob_hashes).await?;
+            }
+            if header.number <= l1_start {
+                break;
+            }
+            l1_hash = header.parent_hash;
+        }
+        info!(
+            target: TARGET,
+            "Collected L1 blocks {} to {}", l1_start, inputs.l1_head
+        );
+
+        self.collect_block(Chain::L2, inputs.l2_head).await?;
+        for number in agreed_output.block_ref.number + 1..=inputs.l2_block_number {
+            self.collect_execution_witness(number).await?;
+        }
+        info!(
+            target: TARGET,
+            "Collected {} preimages for L2 blocks {} to {}",
+            self.witness_data.len(),
+            agreed_output.block_ref.number + 1,
+            inputs.l2_block_number
+        );
+
+        Ok(self.witness_data)
+    }
+
+    /// Records the preimage of the agreed L2 output root.
+    fn collect_output_root(&mut self, output: &OutputResponse) -> Result<()> {
+        let mut preimage = Vec::with_capacity(OUTPUT_ROOT_PREIMAGE_SIZE);
+        preimage.extend_from_slice(output.version.as_slice());
+        preimage.extend_from_slice(output.state_root.as_slice());
+        preimage.extend_from_slice(output.withdrawal_storage_root.as_slice());
+        preimage.extend_from_slice(output.block_ref.hash.as_slice());
+        if keccak256(&preimage) != output.output_root {
+            return Err(eyre!(
+                "Output root {} does not match its preimage",
+                output.output_root
+            ));
+        }
+        self.insert_keccak(Bytes::from(preimage));
+        Ok(())
+    }
+
+    /// Records the header and transactions trie of the given block, along with its receipts
+    /// trie for L1 blocks. Returns the decoded header and the encoded transactions.
+    async fn collect_block(
+        &mut self,
+        chain: Chain,
+        hash: B256,
+    ) -> Result<(BlockHeader, Vec<Bytes>)> {
+        debug!(target: TARGET, "Collecting {:?} block {}", chain, hash);
+        let provider = match chain {
+            Chain::L1 => &self.l1,
+            Chain::L2 => &self.l2,
+        };
+
+        let block: Bytes = raw_request(provider, "debug_getRawBlock", hash).await?;
+        let items = rlp_items(&block)?;
+        let (Some(raw_header), Some(raw_transactions)) = (items.first(), items.get(1)) else {
+            return Err(eyre!("Invalid block {}", hash));
+        };
+        if keccak256(raw_header) != hash {
+            return Err(eyre!("Block {} does not match its header", hash));
+        }
+        let header = BlockHeader::decode_exact(raw_header)?;
+
+        // Legacy transactions are embedded as RLP lists, typed transactions as RLP strings.
+        let transactions = rlp_items(raw_transactions)?
+            .into_iter()
+            .map(|tx| {
+                if tx.first().is_some_and(|&b| b >= alloy_rlp::EMPTY_LIST_CODE) {
+                    Ok(Bytes::copy_from_slice(tx))
+                } else {
+                    rlp_string(tx).map(Bytes::copy_from_slice)
+                }
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let receipts: Option<Vec<Bytes>> = match chain {
+            Chain::L1 => Some(raw_request(provider, "debug_getRawReceipts", hash).await?),
+            Chain::L2 => None,
+        };
+
+        self.insert_keccak(Bytes::copy_from_slice(raw_header));
+        if ordered_trie(&transactions, &mut self.witness_data) != header.transactions_root {
+            return Err(eyre!("Block {} does not match its transactions", hash));
+        }
+        if let Some(receipts) = receipts {
+            if ordered_trie(&receipts, &mut self.witness_data) != header.receipts_root {
+                return Err(eyre!("Block {} does not match its receipts", hash));
+            }
+        }
+
+        Ok((header, transactions))
+    }
+
+    /// Records the blobs with the given versioned hashes from the sidecars of the given L1
+    /// block: the KZG commitment of each blob under its versioned hash, and every field
+    /// element under the hash of the commitment and the root of unity it is evaluated at.
+    async fn collect_blobs(&mut self, header: &BlockHeader, blob_hashes: &[B256]) -> Result<()> {
+        debug!(
+            target: TARGET,
+            "Collecting {} blobs of L1 block {}",
+            blob_hashes.len(),
+            header.number
+        );
+        let sidecars = self.beacon.blob_sidecars(header.timestamp).await?;
+        let roots_of_unity = roots_of_unity();
+        for blob_hash in blob_hashes {
+            let sidecar = sidecars
+                .iter()
+                .find(|sidecar| versioned_hash(&sidecar.kzg_commitment) == *blob_hash)
+                .ok_or_else(|| eyre!("Missing blob {} of L1 block {}", blob_hash, header.number))?;
+            if sidecar.blob.len() != FIELD_ELEMENTS_PER_BLOB * 32 {
+                return Err(eyre!("Blob {} is {} bytes", blob_hash, sidecar.blob.len()));
+            }
+
+            self.witness_data.insert(
+                PreimageKey::new(*blob_hash, PreimageKeyType::Sha256).into(),
+                sidecar.kzg_commitment.clone(),
+            );
+            let mut blob_key = [0u8; 80];
+            blob_key[..48].copy_from_slice(&sidecar.kzg_commitment);
+            for (field_element, root) in sidecar.blob.chunks(32).zip(&roots_of_unity) {
+                blob_key[48..].copy_from_slice(&root.to_be_bytes::<32>());
+                let blob_key_hash = keccak256(blob_key);
+                self.insert_keccak(Bytes::copy_from_slice(&blob_key));
+                self.witness_data.insert(
+                    PreimageKey::new(blob_key_hash, PreimageKeyType::Blob).into(),
+                    Bytes::copy_from_slice(field_element),
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Records the state trie nodes, contract code and ancestor headers accessed while
+    /// executing the given L2 block.
+    async fn collect_execution_witness(&mut self, number: u64) -> Result<()> {
+        debug!(target: TARGET, "Collecting execution witness of L2 block {}", number);
+        let witness: Value = raw_request(
+            &self.l2,
+            "debug_executionWitness",
+            format!("0x{:x}", number),
+        )
+        .await?;
+
+        for field in ["state", "codes", "headers"] {
+            // Clients return the preimages either as a list or as a map keyed by their hash.
+            let values: Vec<&Value> = match witness.get(field) {
+                Some(Value::Array(values)) => values.iter().collect(),
+                Some(Value::Object(values)) => values.values().collect(),
+                _ => continue,
+            };
+            for value in values {
+                // Headers are only recorded when they are returned RLP encoded.
+                if let Ok(preimage) = serde_json::from_value::<Bytes>(value.clone()) {
+                    self.insert_keccak(preimage);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Records the given data as a keccak256 preimage.
+    fn insert_keccak(&mut self, data: Bytes) {
+        self.witness_data.insert(keccak_key(keccak256(&data)), data);
+    }
+}
+
+/// The chain to collect a block from.
+#[derive(Debug, Clone, Copy)]
+enum Chain {
+    L1,
+    L2,
+}
+
+/// A client of the beacon node API, serving the blob sidecars of L1 blocks.
+#[derive(Debug)]
+struct BeaconClient {
+    /// The HTTP client.
+    client: reqwest::Client,
+    /// The URL of the beacon node.
+    url: Url,
+    /// The genesis time and the seconds per slot of the beacon chain, once fetched.
+    slot_timing: Option<(u64, u64)>,
+}
+
+/// The response of the beacon node API, wrapping its data.
+#[derive(Debug, Deserialize)]
+struct BeaconResponse<T> {
+    data: T,
+}
+
+/// The genesis of the beacon chain.
+#[derive(Debug, Deserialize)]
+struct BeaconGenesis {
+    genesis_time: String,
+}
+
+/// A blob sidecar, as served by the beacon node API.
+#[derive(Debug, Deserialize)]
+struct BlobSidecar {
+    blob: Bytes,
+    kzg_commitment: Bytes,
+}
+
+impl BeaconClient {
+    /// Creates a new [BeaconClient] from the beacon node URL.
+    fn new(url: Url) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            url,
+            slot_timing: None,
+        }
+    }
+
+    /// Sends a GET request to the given path of the beacon node API and decodes its data.
+    async fn get<R>(&self, path: &str) -> Result<R>
+    where
+        R: serde::de::DeserializeOwned,
+    {
+        let body = self
+            .client
+            .get(format!(
+                "{}/{}",
+                self.url.as_str().trim_end_matches('/'),
+                path
+            ))
+            .send()
+            .await?
+            .error_for_status()
+            .map_err(|e| eyre!("{} request failed: {}", path, e))?
+            .bytes()
+            .await?;
+        let response: BeaconResponse<R> = serde_json::from_slice(&body)?;
+        Ok(response.data)
+    }
+
+    /// Returns the blob sidecars of the L1 block with the given timestamp.
+    async fn blob_sidecars(&mut self, timestamp: u64) -> Result<Vec<BlobSidecar>> {
+        let (genesis_time, seconds_per_slot) = match self.slot_timing {
+            Some(slot_timing) => slot_timing,
+            None => {
+                let genesis: BeaconGenesis = self.get("eth/v1/beacon/genesis").await?;
+                let spec: Value = self.get("eth/v1/config/spec").await?;
+                let seconds_per_slot = spec["SECONDS_PER_SLOT"]
+                    .as_str()
+                    .ok_or_else(|| eyre!("Missing SECONDS_PER_SLOT in the beacon spec"))?;
+                let slot_timing = (genesis.genesis_time.parse()?, seconds_per_slot.parse()?);
+                self.slot_timing = Some(slot_timing);
+                slot_timing
+            }
+        };
+
+        let slot = timestamp
+            .checked_sub(genesis_time)
+            .ok_or_else(|| eyre!("L1 block at {} predates the beacon chain", timestamp))?
+            / seconds_per_slot;
+        self.get(&format!("eth/v1/beacon/blob_sidecars/{}", slot))
+            .await
+    }
+}
+
+/// Returns the versioned hashes of the blobs of the blob transactions sent to the batch inbox.
+fn batch_blob_hashes(transactions: &[Bytes], batch_inbox: Address) -> Result<Vec<B256>> {
+    let mut blob_hashes = Vec::new();
+    for tx in transactions {
+        let Some(fields) = tx.strip_prefix(&[BLOB_TX_TYPE]) else {
+            continue;
+        };
+        // [chain_id, nonce, max_priority_fee_per_gas, max_fee_per_gas, gas_limit, to, value,
+        //  data, access_list, max_fee_per_blob_gas, blob_versioned_hashes, y_parity, r, s]
+        let fields = rlp_items(fields)?;
+        let (Some(to), Some(hashes)) = (fields.get(5), fields.get(10)) else {
+            return Err(eyre!("Invalid blob transaction"));
+        };
+        if rlp_string(to)? != batch_inbox.as_slice() {
+            continue;
+        }
+        for hash in rlp_items(hashes)? {
+            blob_hashes.push(B256::try_from(rlp_string(hash)?)?);
+        }
+    }
+    Ok(blob_hashes)
+}
+
+/// Returns the versioned hash of a KZG commitment.
+fn versioned_hash(commitment: &[u8]) -> B256 {
+    let mut hash = B256::from_slice(&Sha256::digest(commitment));
+    hash[0] = BLOB_COMMITMENT_VERSION_KZG;
+    hash
+}
+
+/// Returns the roots of unity at which the field elements of a blob are evaluated, in the
+/// bit-reversed order of the field elements.
+fn roots_of_unity() -> Vec<U256> {
+    let order = U256::from(FIELD_ELEMENTS_PER_BLOB);
+    let root = PRIMITIVE_ROOT_OF_UNITY.pow_mod((BLS_MODULUS - U256::from(1)) / order, BLS_MODULUS);
+    let mut roots = Vec::with_capacity(FIELD_ELEMENTS_PER_BLOB);
+    let mut power = U256::from(1);
+    for _ in 0..FIELD_ELEMENTS_PER_BLOB {
+        roots.push(power);
+        power = power.mul_mod(root, BLS_MODULUS);
+    }
+
+    let bits = FIELD_ELEMENTS_PER_BLOB.trailing_zeros();
+    (0..FIELD_ELEMENTS_PER_BLOB)
+        .map(|i| roots[i.reverse_bits() >> (usize::BITS - bits)])
+        .collect()
+}
+
+/// Sends a JSON-RPC request with a single parameter and decodes its result.
+async fn raw_request<P, R>(provider: &ReqwestProvider, method: &'static str, param: P) -> Result<R>
+where
+    P: serde::Serialize + Send + Sync + Clone,
+    R: serde::de::DeserializeOwned,
+{
+    let result: Value = provider
+        .raw_request(method.into(), (param,))
+        .await
+        .map_err(|e| eyre!("{} request failed: {}", method, e))?;
+    Ok(serde_json::from_value(result)?)
+}
+
+/// Compares the witness data collected by two collectors, returning a description of every
+/// preimage key type whose witnesses differ.
+pub fn compare_witness_data(
+    expected: &BTreeMap<B256, Bytes>,
+    actual: &BTreeMap<B256, Bytes>,
+) -> Vec<String> {
+    let mut missing = BTreeMap::<String, usize>::new();
+    let mut extra = BTreeMap::<String, usize>::new();
+    let mut mismatched = 0;
+    for (key, value) in expected {
+        match actual.get(key) {
+            None => *missing.entry(key_type_name(key)).or_default() += 1,
+            Some(actual) if actual != value => mismatched += 1,
+            Some(_) => {}
+        }
+    }
+    for key in actual.keys().filter(|key| !expected.contains_key(*key)) {
+        *extra.entry(key_type_name(key)).or_default() += 1;
+    }
+
+    let mut differences = Vec::new();
+    for (key_type, count) in missing {
+        differences.push(format!("{} {} preimages are missing", count, key_type));
+    }
+    for (key_type, count) in extra {
+        differences.push(format!("{} {} preimages are extra", count, key_type));
+    }
+    if mismatched > 0 {
+        differences.push(format!("{} preimages have different values", mismatched));
+    }
+    differences
+}
+
+/// Returns the name of the preimage key type of the given key.
+fn key_type_name(key: &B256) -> String {
+    PreimageKey::try_from(*key)
+        .map(|key| key.key_type().to_string())
+        .unwrap_or_else(|_| "unknown".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cmd::witness::encode_list;
+
+    #[test]
+    fn test_compare_witness_data() {
+        let mut expected = BTreeMap::new();
+        expected.insert(B256::from([2; 32]), Bytes::from_static(&[1]));
+        expected.insert(B256::from([4; 32]), Bytes::from_static(&[2]));
+        expected.insert(B256::from([5; 32]), Bytes::from_static(&[3]));
+
+        let mut actual = BTreeMap::new();
+        actual.insert(B256::from([2; 32]), Bytes::from_static(&[1]));
+        actual.insert(B256::from([5; 32]), Bytes::from_static(&[4]));
+        actual.insert(B256::from([9; 32]), Bytes::from_static(&[5]));
+
+        assert_eq!(
+            compare_witness_data(&expected, &expected),
+            Vec::<String>::new()
+        );
+        assert_eq!(
+            compare_witness_data(&expected, &actual),
+            vec![
+                "1 sha256 preimages are missing".to_string(),
+                "1 unknown preimages are extra".to_string(),
+                "1 preimages have different values".to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_roots_of_unity() {
+        let roots = roots_of_unity();
+        let one = U256::from(1);
+        assert_eq!(roots.len(), FIELD_ELEMENTS_PER_BLOB);
+        assert_eq!(roots[0], one);
+        // The second root in bit-reversed order is the square root of unity.
+        assert_eq!(roots[1], BLS_MODULUS - one);
+        // The next two are the fourth roots of unity, which square to the square root.
+        assert_eq!(roots[2].mul_mod(roots[2], BLS_MODULUS), BLS_MODULUS - one);
+        assert_eq!(roots[3], BLS_MODULUS - roots[2]);
+        let order = U256::from(FIELD_ELEMENTS_PER_BLOB);
+        for root in &roots {
+            assert_eq!(root.pow_mod(order, BLS_MODULUS), one);
+        }
+        assert_eq!(
+            roots[FIELD_ELEMENTS_PER_BLOB / 2].pow_mod(order / U256::from(2), BLS_MODULUS),
+            BLS_MODULUS - one
+        );
+    }
+
+    #[test]
+    fn test_batch_blob_hashes() {
+        let inbox = Address::from([0xff; 20]);
+        let blob_tx = |to: Address, hashes: &[B256]| {
+            let mut fields = vec![vec![0x80]; 14];
+            fields[5] = alloy_rlp::encode(to.as_slice());
+            fields[10] = encode_list(
+                &hashes
+                    .iter()
+                    .map(|hash| alloy_rlp::encode(hash.as_slice()))
+                    .collect::<Vec<_>>(),
+            );
+            let mut tx = vec![BLOB_TX_TYPE];
+            tx.extend(encode_list(&fields));
+            Bytes::from(tx)
+        };
+
+        let hashes = [B256::from([1; 32]), B256::from([2; 32])];
+        let transactions = vec![
+            Bytes::from_static(&[0x02, 0xc0]),
+            blob_tx(Address::from([0xee; 20]), &hashes[..1]),
+            blob_tx(inbox, &hashes),
+        ];
+        assert_eq!(batch_blob_hashes(&transactions, inbox).unwrap(), hashes);
+        assert_eq!(versioned_hash(&[0; 48])[0], BLOB_COMMITMENT_VERSION_KZG);
+    }
+}
@@ -0,0 +1,426 @@
+//! Diff Run Subcommand
+
+use clap::{ArgAction, Parser};
+use color_eyre::{eyre::eyre, Result};
+use fp_test_fixtures::FaultProofStatus;
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeSet, path::PathBuf};
+use tracing::{error, info};
+
+use super::run_op_program::{Program, ProgramArgs, ProgramStats};
+
+/// The logging target to use for [tracing].
+const TARGET: &str = "diff-run";
+
+/// The maximum number of differing witness keys listed in a divergence.
+const MAX_LISTED_KEYS: usize = 8;
+
+/// CLI arguments for the `diff-run` subcommand of `opfp`.
+///
+/// The fixture is always run through the op-program natively. It is also run through
+/// Cannon if `--cannon` is set, with the program selected by `--program` built into the
+/// Cannon state, through kona-client natively if `--kona-client` is set, and through
+/// kona-client within asterisc if `--asterisc` is set.
+#[derive(Parser, Clone, Debug)]
+pub struct DiffRun {
+    /// The fault proof programs to run the fixture with
+    #[command(flatten)]
+    pub program: ProgramArgs,
+    /// Path to the fixture file
+    #[clap(short, long, help = "Path to the fixture file")]
+    pub fixture: PathBuf,
+    /// Optional output file path for the divergence report
+    #[clap(long, help = "Path to the JSON divergence report")]
+    pub output: Option<PathBuf>,
+    /// Verbosity level (0-4)
+    #[arg(long, short, help = "Verbosity level (0-4)", action = ArgAction::Count)]
+    pub v: u8,
+}
+
+/// The outcome of running a fixture through every configured program, along with the
+/// divergences between them.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DiffReport {
+    /// The path to the fixture file.
+    pub fixture: PathBuf,
+    /// The expected status of the fixture, if it could be loaded.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expected_status: Option<FaultProofStatus>,
+    /// The run of each program.
+    pub runs: Vec<ProgramRun>,
+    /// A description of every divergence between the programs and the expected status.
+    pub divergences: Vec<String>,
+}
+
+/// The outcome of running a fixture through a single program.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProgramRun {
+    /// The name of the program.
+    pub program: String,
+    /// The fault proof program implementation, whichever VM it ran in.
+    pub client: Program,
+    /// The stats of the program run, if it completed.
+    #[serde(flatten)]
+    pub stats: Option<ProgramStats>,
+    /// The error that prevented the program from running, if any.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl DiffRun {
+    /// Runs the `diff-run` subcommand.
+    pub async fn run(&self) -> Result<()> {
+        let mut expected_status = None;
+        let mut runs = Vec::new();
+        for program in self.programs() {
+            info!(
+                target: TARGET,
+                "Running {:?} with {}",
+                self.fixture,
+                program.name()
+            );
+            let result = program.run_fixture_file(&self.fixture).await;
+            expected_status = expected_status.or(result.expected_status);
+            runs.push(ProgramRun {
+                program: program.name().to_string(),
+                client: program.program,
+                stats: result.stats,
+                error: result.error,
+            });
+        }
+
+        let report = DiffReport {
+            fixture: self.fixture.clone(),
+            expected_status,
+            divergences: divergences(expected_status, &runs),
+            runs,
+        };
+        print_report(&report);
+
+        if let Some(output) = &self.output {
+            let file = std::fs::File::create(output)?;
+            serde_json::to_writer_pretty(file, &report)?;
+        }
+
+        if !report.divergences.is_empty() {
+            for divergence in &report.divergences {
+                error!(target: TARGET, "{}", divergence);
+            }
+            return Err(eyre!(
+                "Found {} divergences between {} programs",
+                report.divergences.len(),
+                report.runs.len()
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Returns the configuration of every program to run the fixture with.
+    fn programs(&self) -> Vec<ProgramArgs> {
+        let native = ProgramArgs {
+            cannon: None,
+            asterisc: None,
+            ..self.program.clone()
+        };
+
+        let mut programs = vec![ProgramArgs {
+            program: Program::OpProgram,
+            ..native.clone()
+        }];
+        if self.program.cannon.is_some() {
+            programs.push(ProgramArgs {
+                asterisc: None,
+                ..self.program.clone()
+            });
+        }
+        if self.program.kona_client.is_some() {
+            programs.push(ProgramArgs {
+                program: Program::Kona,
+                ..native
+            });
+        }
+        if self.program.asterisc.is_some() {
+            programs.push(ProgramArgs {
+                program: Program::Kona,
+                cannon: None,
+                ..self.program.clone()
+            });
+        }
+        programs
+    }
+}
+
+/// Returns a description of every divergence between the runs of the programs, and between
+/// each program and the expected status.
+pub fn divergences(expected_status: Option<FaultProofStatus>, runs: &[ProgramRun]) -> Vec<String> {
+    let mut divergences = Vec::new();
+
+    for run in runs {
+        match (&run.stats, &run.error, expected_status) {
+            (_, Some(error), _) => {
+                divergences.push(format!("{} failed to run: {}", run.program, error))
+            }
+            (Some(stats), None, Some(expected)) if stats.status != expected => {
+                divergences.push(format!(
+                    "{} exited with status {:?}, expected {:?}",
+                    run.program, stats.status, expected
+                ))
+            }
+            _ => {}
+        }
+    }
+
+    let statuses = runs
+        .iter()
+        .filter_map(|run| Some((run.program.as_str(), run.stats.as_ref()?.status)))
+        .collect::<Vec<_>>();
+    if statuses.windows(2).any(|pair| pair[0].1 != pair[1].1) {
+        divergences.push(format!(
+            "Programs disagree on the status: {}",
+            describe(
+                statuses
+                    .iter()
+                    .map(|(name, status)| (*name, format!("{:?}", status)))
+            )
+        ));
+    }
+
+    // The same program requests the same witness entries natively and within any VM, while
+    // different programs may fetch different preimages for the same fixture. The preimages are
+    // only compared when they are tracked, and the keys which are missing or unused in some
+    // runs but not others are listed.
+    for client in [Program::OpProgram, Program::Kona] {
+        let usages = runs
+            .iter()
+            .filter(|run| run.client == client)
+            .filter_map(|run| {
+                Some((
+                    run.program.as_str(),
+                    run.stats.as_ref()?.preimage_usage.as_ref()?,
+                ))
+            })
+            .collect::<Vec<_>>();
+        let Some((_, first)) = usages.first() else {
+            continue;
+        };
+        let differing = usages
+            .iter()
+            .flat_map(|(_, usage)| {
+                let missing = usage.missing.symmetric_difference(&first.missing);
+                missing.chain(usage.unused.symmetric_difference(&first.unused))
+            })
+            .collect::<BTreeSet<_>>();
+        if !differing.is_empty() {
+            let mut keys = differing
+                .iter()
+                .take(MAX_LISTED_KEYS)
+                .map(|key| key.to_string())
+                .collect::<Vec<_>>();
+            if differing.len() > MAX_LISTED_KEYS {
+                keys.push(format!("{} more", differing.len() - MAX_LISTED_KEYS));
+            }
+            divergences.push(format!(
+                "Runs of {:?} disagree on the witness entries requested: {}, differing in {}",
+                client,
+                describe(usages.iter().map(|(name, usage)| (
+                    *name,
+                    format!(
+                        "left {} unused and {} missing",
+                        usage.unused.len(),
+                        usage.missing.len()
+                    )
+                ))),
+                keys.join(", ")
+            ));
+        }
+    }
+
+    divergences
+}
+
+/// Describes the given value of each program as a comma separated list.
+fn describe<'a>(values: impl Iterator<Item = (&'a str, String)>) -> String {
+    values
+        .map(|(name, value)| format!("{} {}", name, value))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Prints a human-readable summary of the runs to stdout.
+fn print_report(report: &DiffReport) {
+    println!(
+        "{} (expected {:?})",
+        report.fixture.display(),
+        report.expected_status.unwrap_or(FaultProofStatus::Unknown)
+    );
+    for run in &report.runs {
+        match (&run.stats, &run.error) {
+            (Some(stats), _) => println!(
+                "  {}: {:?} ({}ms)",
+                run.program, stats.status, stats.runtime
+            ),
+            (None, Some(error)) => println!("  {}: {}", run.program, error),
+            (None, None) => println!("  {}", run.program),
+        }
+    }
+    if report.divergences.is_empty() {
+        println!("No divergences");
+    }
+    for divergence in &report.divergences {
+        println!("DIVERGENCE {}", divergence);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cmd::run_op_program::PreimageUsage;
+    use alloy_primitives::B256;
+
+    fn run(
+        program: &str,
+        client: Program,
+        status: FaultProofStatus,
+        unused: Option<&[u8]>,
+        missing: &[u8],
+    ) -> ProgramRun {
+        ProgramRun {
+            program: program.to_string(),
+            client,
+            stats: Some(ProgramStats {
+                status,
+                preimage_usage: unused.map(|unused| PreimageUsage {
+                    unused: unused.iter().map(|&key| B256::from([key; 32])).collect(),
+                    missing: missing.iter().map(|&key| B256::from([key; 32])).collect(),
+                    ..PreimageUsage::default()
+                }),
+                ..ProgramStats::default()
+            }),
+            error: None,
+        }
+    }
+
+    #[test]
+    fn test_divergences() {
+        // Different programs may request different preimages, and untracked runs are not
+        // compared.
+        let agreeing = vec![
+            run(
+                "op-program",
+                Program::OpProgram,
+                FaultProofStatus::Valid,
+                None,
+                &[],
+            ),
+            run(
+                "cannon",
+                Program::OpProgram,
+                FaultProofStatus::Valid,
+                Some(&[1]),
+                &[],
+            ),
+            run(
+                "kona",
+                Program::Kona,
+                FaultProofStatus::Valid,
+                Some(&[2, 3]),
+                &[],
+            ),
+            run(
+                "kona-cannon",
+                Program::Kona,
+                FaultProofStatus::Valid,
+                Some(&[2, 3]),
+                &[],
+            ),
+        ];
+        assert!(divergences(Some(FaultProofStatus::Valid), &agreeing).is_empty());
+
+        let diverging = vec![
+            run(
+                "op-program",
+                Program::OpProgram,
+                FaultProofStatus::Valid,
+                Some(&[1]),
+                &[],
+            ),
+            run(
+                "cannon",
+                Program::OpProgram,
+                FaultProofStatus::Valid,
+                Some(&[1]),
+                &[],
+            ),
+            run(
+                "kona-asterisc",
+                Program::Kona,
+                FaultProofStatus::Valid,
+                Some(&[1]),
+                &[],
+            ),
+            run(
+                "kona-cannon",
+                Program::Kona,
+                FaultProofStatus::Invalid,
+                Some(&[1, 2]),
+                &[],
+            ),
+            ProgramRun {
+                program: "kona".to_string(),
+                client: Program::Kona,
+                stats: None,
+                error: Some("Missing kona-client binary".to_string()),
+            },
+        ];
+        assert_eq!(
+            divergences(Some(FaultProofStatus::Valid), &diverging),
+            vec![
+                "kona-cannon exited with status Invalid, expected Valid".to_string(),
+                "kona failed to run: Missing kona-client binary".to_string(),
+                "Programs disagree on the status: op-program Valid, cannon Valid, \
+                 kona-asterisc Valid, kona-cannon Invalid"
+                    .to_string(),
+                format!(
+                    "Runs of Kona disagree on the witness entries requested: kona-asterisc left 1 \
+                     unused and 0 missing, kona-cannon left 2 unused and 0 missing, differing \
+                     in {}",
+                    B256::from([2; 32])
+                ),
+            ]
+        );
+
+        // Runs leaving the same number of entries unused still diverge on different keys, and
+        // only a sample of the differing keys is listed.
+        let many = (10..30).collect::<Vec<u8>>();
+        let diverging = vec![
+            run(
+                "op-program",
+                Program::OpProgram,
+                FaultProofStatus::Valid,
+                Some(&[1]),
+                &[4],
+            ),
+            run(
+                "cannon",
+                Program::OpProgram,
+                FaultProofStatus::Valid,
+                Some(&[2]),
+                &many,
+            ),
+        ];
+        let listed = [1, 2, 4, 10, 11, 12, 13, 14]
+            .map(|key| B256::from([key; 32]).to_string())
+            .join(", ");
+        assert_eq!(
+            divergences(Some(FaultProofStatus::Valid), &diverging),
+            vec![format!(
+                "Runs of OpProgram disagree on the witness entries requested: op-program left 1 \
+                 unused and 1 missing, cannon left 1 unused and 20 missing, differing in {}, \
+                 15 more",
+                listed
+            )]
+        );
+    }
+}
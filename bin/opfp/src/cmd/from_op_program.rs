@@ -2,28 +2,30 @@
 
 use alloy_primitives::hex::FromHex;
 use alloy_primitives::BlockHash;
-use alloy_primitives::{hex::ToHexExt, B256};
-use clap::{ArgAction, Parser};
+use alloy_primitives::{hex::ToHexExt, Bytes, B256};
+use clap::{ArgAction, Parser, ValueEnum};
 use color_eyre::{eyre::eyre, Result};
 use fp_test_fixtures::{
     self, ChainDefinition, FaultProofFixture, FaultProofInputs, FaultProofStatus, Genesis,
+    FIXTURE_VERSION,
 };
 use kona_derive::online::*;
 use reqwest::Url;
 use std::collections::BTreeMap;
 use std::sync::Arc;
-use std::time::{SystemTime, UNIX_EPOCH};
 use std::{
-    env,
     io::{stderr, stdout},
-    path::PathBuf,
+    path::{Path, PathBuf},
 };
 use superchain_registry::ROLLUP_CONFIGS;
-use tracing::{debug, error, info, trace};
+use tracing::{debug, error, info, trace, warn};
 
 use crate::cmd::util::RollupConfig;
 
+use super::collector::{compare_witness_data, NativeCollector};
+use super::run_op_program::{FaultProofRunner, OpProgramCommand};
 use super::util::{RollupProvider, SafeHeadResponse};
+use super::workspace::WorkspaceArgs;
 
 /// The logging target to use for [tracing].
 const TARGET: &str = "from-op-program";
@@ -31,12 +33,55 @@ const TARGET: &str = "from-op-program";
 /// CLI arguments for the `from-op-program` subcommand of `opfp`.
 #[derive(Parser, Clone, Debug)]
 pub struct FromOpProgram {
-    /// The path to the op-program binary.
-    #[clap(short, long, help = "Path to the op-program binary")]
-    pub op_program: PathBuf,
-    /// The L2 block number to validate.
-    #[clap(long, help = "L2 block number to validate")]
-    pub l2_block: u64,
+    /// The path to the op-program binary, used to collect the witness data or to verify the
+    /// natively collected witness data.
+    #[clap(
+        short,
+        long,
+        help = "Path to the op-program binary, used to collect the witness data or to verify the \
+                natively collected witness data"
+    )]
+    pub op_program: Option<PathBuf>,
+    /// The collector used to gather the witness data.
+    #[clap(
+        long,
+        value_enum,
+        default_value_t = Collector::OpProgram,
+        help = "Collector used to gather the witness data"
+    )]
+    pub collector: Collector,
+    /// The data directories of the op-program runs
+    #[command(flatten)]
+    pub workspace: WorkspaceArgs,
+    /// The L2 block number to validate, starting from the output root of its parent block.
+    #[clap(
+        long,
+        required_unless_present = "l2_end",
+        conflicts_with_all = ["l2_start", "l2_end"],
+        help = "L2 block number to validate"
+    )]
+    pub l2_block: Option<u64>,
+    /// The L2 block number of the agreed output root, when validating a range of blocks.
+    #[clap(
+        long,
+        requires = "l2_end",
+        help = "L2 block number of the agreed output root, when validating a range of blocks"
+    )]
+    pub l2_start: Option<u64>,
+    /// The last L2 block number to validate, when validating a range of blocks.
+    #[clap(
+        long,
+        requires = "l2_start",
+        help = "Last L2 block number to validate, when validating a range of blocks"
+    )]
+    pub l2_end: Option<u64>,
+    /// Optional number of blocks per fixture, splitting the range into multiple fixtures.
+    #[clap(
+        long,
+        requires = "l2_end",
+        help = "Split the range into one fixture per span of this many blocks"
+    )]
+    pub split_every: Option<u64>,
     /// Optional L1 block number which can derive the given L2 block.
     #[clap(
         long,
@@ -64,29 +109,183 @@ pub struct FromOpProgram {
     /// Optional path to the genesis file.
     #[clap(long, help = "Optional path to the genesis file")]
     pub genesis_path: Option<PathBuf>,
+    /// Optional output root to claim instead of the honest output root.
+    #[clap(
+        long,
+        conflicts_with_all = ["corrupt_claim", "claim_block"],
+        help = "Output root to claim instead of the honest output root"
+    )]
+    pub claim: Option<B256>,
+    /// Claim a corrupted copy of the honest output root.
+    #[clap(
+        long,
+        conflicts_with = "claim_block",
+        help = "Claim a corrupted copy of the honest output root"
+    )]
+    pub corrupt_claim: bool,
+    /// Optional L2 block number whose output root is claimed instead of the honest output root.
+    #[clap(
+        long,
+        help = "L2 block number whose output root is claimed instead of the honest output root"
+    )]
+    pub claim_block: Option<u64>,
     /// The output file for the test fixture.
-    #[clap(long, help = "Output file for the test fixture")]
+    #[clap(
+        long,
+        help = "Output file for the test fixture, encoded according to its extension (.json, .json.gz, .json.zst, .bin). When splitting a range, the span of each fixture is appended to the file name"
+    )]
     pub output: PathBuf,
     /// Verbosity level (0-4)
     #[arg(long, short, help = "Verbosity level (0-4)", action = ArgAction::Count)]
     pub v: u8,
 }
 
+/// The collector used to gather the witness data of a fixture.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Collector {
+    /// Runs the op-program host and collects the preimages it writes to its data directory.
+    OpProgram,
+    /// Collects the preimages natively from the L1 and L2 execution clients and the L1
+    /// beacon client, then verifies them by running the fixture through the op-program.
+    Native,
+    /// Runs both collectors, writing the op-program witness data and reporting the
+    /// differences between them.
+    CrossCheck,
+}
+
 impl FromOpProgram {
     /// Runs the from-op-program subcommand.
     pub async fn run(&self) -> Result<()> {
-        trace!(target: TARGET, "Producing fault proof fixture for L2 block {}", self.l2_block);
+        let (start, end) = match (self.l2_block, self.l2_start, self.l2_end) {
+            (Some(l2_block), None, None) => (
+                l2_block
+                    .checked_sub(1)
+                    .ok_or_else(|| eyre!("Cannot validate the L2 genesis block"))?,
+                l2_block,
+            ),
+            (None, Some(start), Some(end)) => (start, end),
+            _ => {
+                return Err(eyre!(
+                    "Either --l2-block or --l2-start and --l2-end are required"
+                ))
+            }
+        };
+
+        let spans = block_spans(start, end, self.split_every)?;
+        for &(agreed_block, claimed_block) in &spans {
+            let output = if self.split_every.is_some() {
+                span_output_path(&self.output, agreed_block, claimed_block)
+            } else {
+                self.output.clone()
+            };
+            self.produce_fixture(agreed_block, claimed_block, &output)
+                .await?;
+        }
+        info!(target: TARGET, "Produced {} fault proof fixtures", spans.len());
+
+        Ok(())
+    }
 
-        let inputs = self.fault_proof_inputs().await?;
+    /// Produces a fixture which validates the L2 blocks after `agreed_block` up to and
+    /// including `claimed_block`, and writes it to the given output path.
+    async fn produce_fixture(
+        &self,
+        agreed_block: u64,
+        claimed_block: u64,
+        output: &Path,
+    ) -> Result<()> {
+        trace!(
+            target: TARGET,
+            "Producing fault proof fixture for L2 blocks {} to {}",
+            agreed_block + 1,
+            claimed_block
+        );
+
+        let mut inputs = self.fault_proof_inputs(agreed_block, claimed_block).await?;
+        let honest_claim = inputs.l2_claim;
+        inputs.l2_claim = self.claim(honest_claim, claimed_block).await?;
+        let expected_status = if inputs.l2_claim == honest_claim {
+            FaultProofStatus::Valid
+        } else {
+            info!(
+                target: TARGET,
+                "Claiming {} instead of the honest output root {}", inputs.l2_claim, honest_claim
+            );
+            FaultProofStatus::Invalid
+        };
         debug!(target: TARGET, "Using the following fault proof inputs: {:?}", inputs);
 
-        let dirname = SystemTime::now()
-            .duration_since(UNIX_EPOCH)?
-            .as_millis()
-            .to_string();
-        let data_dir = env::temp_dir().join("from-op-program").join(dirname);
-        std::fs::create_dir_all(&data_dir)?;
+        let witness_data = match self.collector {
+            Collector::OpProgram => self.op_program_witness(&inputs, honest_claim)?,
+            Collector::Native => self.native_witness(&inputs, agreed_block).await?,
+            Collector::CrossCheck => {
+                let witness_data = self.op_program_witness(&inputs, honest_claim)?;
+                let native_witness_data = self.native_witness(&inputs, agreed_block).await?;
+                let differences = compare_witness_data(&witness_data, &native_witness_data);
+                if differences.is_empty() {
+                    info!(target: TARGET, "Native witness data matches the op-program");
+                }
+                for difference in differences {
+                    warn!(target: TARGET, "Native witness data differs: {}", difference);
+                }
+                witness_data
+            }
+        };
+
+        let fixture = FaultProofFixture {
+            version: FIXTURE_VERSION,
+            inputs,
+            expected_status,
+            witness_data,
+        };
+        // The witness data of an invalid claim is collected on the honest claim, so only an
+        // offline run shows that the host rejects the claim rather than failing on the data.
+        if self.collector == Collector::Native || expected_status == FaultProofStatus::Invalid {
+            self.verify_fixture(&fixture).await?;
+        }
+        info!(target: TARGET, "Successfully built fault proof test fixture");
+
+        // Write the fault proof fixture to the specified output location, using the encoding
+        // of its file extension.
+        fixture.save(output)?;
+        info!(target: TARGET, "Wrote fault proof fixture to: {:?}", output);
+
+        Ok(())
+    }
 
+    /// Runs the op-program host against the RPCs on the honest claim and collects the
+    /// preimages it fetched from its data directory. The claim is only compared once the L2
+    /// blocks have been derived and executed, so the same preimages validate any claim.
+    fn op_program_witness(
+        &self,
+        inputs: &FaultProofInputs,
+        honest_claim: B256,
+    ) -> Result<BTreeMap<B256, Bytes>> {
+        let op_program = self.op_program.as_ref().ok_or_else(|| {
+            eyre!(
+                "The op-program binary is required by the {:?} collector",
+                self.collector
+            )
+        })?;
+
+        let honest_inputs = FaultProofInputs {
+            l2_claim: honest_claim,
+            ..inputs.clone()
+        };
+        let data_dir = self.workspace.create(TARGET)?;
+        let witness_data = self.run_op_program(op_program, &honest_inputs, data_dir.path());
+        data_dir.finish(witness_data.is_ok());
+        witness_data
+    }
+
+    /// Runs the op-program host with the given data directory, returning the preimages it
+    /// fetched. Fails unless the host validates the claim.
+    fn run_op_program(
+        &self,
+        op_program: &Path,
+        inputs: &FaultProofInputs,
+        data_dir: &Path,
+    ) -> Result<BTreeMap<B256, Bytes>> {
         let input_dir = data_dir.join("input");
         if input_dir.exists() {
             std::fs::remove_dir_all(&input_dir)?;
@@ -102,7 +301,7 @@ impl FromOpProgram {
         }
         info!(target: TARGET, "Created output temp directory: {:?}", output_dir);
 
-        let mut command = std::process::Command::new(&self.op_program);
+        let mut command = std::process::Command::new(op_program);
         match &inputs.chain_definition {
             ChainDefinition::Named(name) => {
                 command.arg("--network").arg(name);
@@ -169,7 +368,10 @@ impl FromOpProgram {
             .map_err(|e| eyre!(e))?;
 
         if !status.success() {
-            error!(target: TARGET, "Failed to execute op-program binary");
+            error!(
+                target: TARGET,
+                "op-program exited with {} on the honest claim", status
+            );
             return Err(eyre!("Failed to execute op-program binary"));
         }
 
@@ -219,21 +421,109 @@ impl FromOpProgram {
             })
         })?;
 
-        let fixture = FaultProofFixture {
-            inputs,
-            expected_status: FaultProofStatus::Valid,
-            witness_data,
-        };
-        info!(target: TARGET, "Successfully built fault proof test fixture");
+        Ok(witness_data)
+    }
+
+    /// Collects the preimages natively from the L1 and L2 execution clients and the L1 beacon
+    /// client.
+    async fn native_witness(
+        &self,
+        inputs: &FaultProofInputs,
+        agreed_block: u64,
+    ) -> Result<BTreeMap<B256, Bytes>> {
+        let cfg = self.rollup_config().await?;
+        let agreed_output = self
+            .rollup_provider()?
+            .output_at_block(agreed_block)
+            .await?;
+
+        // Derivation may need channel frames from L1 blocks before the L1 origin of the
+        // agreed L2 block, up to the channel timeout.
+        NativeCollector::new_http(self.l1_rpc_url()?, self.l2_rpc_url()?, self.beacon_url()?)
+            .collect(
+                inputs,
+                &agreed_output,
+                cfg.channel_timeout_bedrock,
+                cfg.batch_inbox_address,
+            )
+            .await
+    }
 
-        // Write the fault proof fixture to the specified output location.
-        let file = std::fs::File::create(&self.output)?;
-        serde_json::to_writer_pretty(file, &fixture)?;
-        info!(target: TARGET, "Wrote fault proof fixture to: {:?}", self.output);
+    /// Runs the fixture through the op-program once, without access to the RPCs, failing if
+    /// it does not reach its expected status. A run missing any preimage from the witness data
+    /// fails, including for an invalid claim: the host exits with the same code on a missing
+    /// preimage as on an invalid claim, but only rejects the claim once it has run to
+    /// completion.
+    async fn verify_fixture(&self, fixture: &FaultProofFixture) -> Result<()> {
+        let op_program = self.op_program.as_ref().ok_or_else(|| {
+            eyre!(
+                "The op-program binary is required to verify the witness data of the {:?} \
+                 collector",
+                self.collector
+            )
+        })?;
 
+        let data_dir = self.workspace.create(TARGET)?;
+        let command = OpProgramCommand::new(
+            op_program.clone(),
+            fixture.clone(),
+            data_dir.path().to_path_buf(),
+            None,
+        );
+        let stats = match command.prepare().await {
+            Ok(()) => command.run().await,
+            Err(e) => Err(e),
+        };
+        data_dir.finish(
+            stats
+                .as_ref()
+                .is_ok_and(|stats| stats.status == fixture.expected_status),
+        );
+
+        let status = stats
+            .map_err(|e| {
+                eyre!(
+                    "The collected witness data failed to run, preimages are likely missing                      from it: {}",
+                    e
+                )
+            })?
+            .status;
+        if status != fixture.expected_status {
+            return Err(eyre!(
+                "The collected witness data reached status {:?} instead of {:?}, preimages are \
+                 likely missing from it",
+                status,
+                fixture.expected_status
+            ));
+        }
+        info!(
+            target: TARGET,
+            "Verified the collected witness data reaches status {:?}", status
+        );
         Ok(())
     }
 
+    /// Returns the output root to claim, given the honest output root of the claimed L2 block.
+    async fn claim(&self, honest_claim: B256, claimed_block: u64) -> Result<B256> {
+        if let Some(claim) = self.claim {
+            return Ok(claim);
+        }
+        if self.corrupt_claim {
+            return Ok(corrupt_claim(honest_claim));
+        }
+        if let Some(claim_block) = self.claim_block {
+            if claim_block == claimed_block {
+                warn!(
+                    target: TARGET,
+                    "Claim block {} is the validated L2 block, the claim will be valid", claim_block
+                );
+            }
+            let output = self.rollup_provider()?.output_at_block(claim_block).await?;
+            return Ok(output.output_root);
+        }
+        Ok(honest_claim)
+    }
+
     /// Returns a new [AlloyChainProvider] using the l1 rpc url.
     pub fn l1_provider(&self) -> Result<AlloyChainProvider> {
         Ok(AlloyChainProvider::new_http(self.l1_rpc_url()?))
@@ -286,17 +576,21 @@ impl FromOpProgram {
     }
 
     /// Returns the beacon url from CLI or environment variable.
-    pub fn beacon_url(&self) -> String {
-        self.beacon_url.clone()
+    pub fn beacon_url(&self) -> Result<Url> {
+        Url::parse(&self.beacon_url).map_err(|e| eyre!(e))
     }
 
-    async fn fault_proof_inputs(&self) -> Result<FaultProofInputs> {
+    async fn fault_proof_inputs(
+        &self,
+        agreed_block: u64,
+        claimed_block: u64,
+    ) -> Result<FaultProofInputs> {
         let cfg = self.rollup_config().await?;
 
         let rollup_provider = self.rollup_provider()?;
 
-        let claim_output = rollup_provider.output_at_block(self.l2_block).await?;
-        let parent_output = rollup_provider.output_at_block(self.l2_block - 1).await?;
+        let claim_output = rollup_provider.output_at_block(claimed_block).await?;
+        let parent_output = rollup_provider.output_at_block(agreed_block).await?;
 
         let chain_definition: ChainDefinition;
 
@@ -322,7 +616,7 @@ impl FromOpProgram {
                 .map_err(|_| eyre!("Failed to fetch L1 block info"))?
                 .hash;
         } else {
-            let next_safe_head = self.find_next_safe_head().await?;
+            let next_safe_head = self.find_next_safe_head(claimed_block).await?;
             l1_head = next_safe_head.l1_block.hash;
         }
 
@@ -336,12 +630,12 @@ impl FromOpProgram {
         })
     }
 
-    async fn find_next_safe_head(&self) -> Result<SafeHeadResponse> {
+    async fn find_next_safe_head(&self, l2_block: u64) -> Result<SafeHeadResponse> {
         let cfg = self.rollup_config().await?;
         let mut l2_provider = self.l2_provider(Arc::new(cfg.into()))?;
 
         let l2_block_info = l2_provider
-            .l2_block_info_by_number(self.l2_block)
+            .l2_block_info_by_number(l2_block)
             .await
             .map_err(|_| eyre!("Failed to fetch L2 block info"))?;
         let mut l1_block_num = l2_block_info.l1_origin.number;
@@ -352,10 +646,138 @@ impl FromOpProgram {
         for _ in 0..10 {
             l1_block_num += skip_size;
             let next_safe_head = rollup_provider.safe_head_at_block(l1_block_num).await?;
-            if next_safe_head.safe_head.number >= self.l2_block {
+            if next_safe_head.safe_head.number >= l2_block {
                 return Ok(next_safe_head);
             }
         }
         Err(eyre!("No next safe head found"))
     }
 }
+
+/// Returns a copy of the given output root with its lowest bit flipped.
+fn corrupt_claim(claim: B256) -> B256 {
+    let mut corrupted = claim;
+    corrupted[31] ^= 1;
+    corrupted
+}
+
+/// Splits the L2 block range after `start` up to and including `end` into spans of at most
+/// `split_every` blocks, returning the agreed and claimed block of each span.
+fn block_spans(start: u64, end: u64, split_every: Option<u64>) -> Result<Vec<(u64, u64)>> {
+    if end <= start {
+        return Err(eyre!(
+            "The last L2 block {} must be after the agreed L2 block {}",
+            end,
+            start
+        ));
+    }
+    match split_every {
+        None => Ok(vec![(start, end)]),
+        Some(0) => Err(eyre!("The number of blocks per fixture must be at least 1")),
+        Some(n) => Ok((start..end)
+            .step_by(n as usize)
+            .map(|agreed| (agreed, end.min(agreed + n)))
+            .collect()),
+    }
+}
+
+/// Returns the output path of the fixture for a span of a split range, appending the span to
+/// the file name before its extensions, e.g. `Writer.json` becomes `Writer-100-101.json`.
+fn span_output_path(output: &Path, agreed_block: u64, claimed_block: u64) -> PathBuf {
+    let file_name = output
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let (stem, extensions) = file_name.split_at(file_name.find('.').unwrap_or(file_name.len()));
+    output.with_file_name(format!(
+        "{}-{}-{}{}",
+        stem, agreed_block, claimed_block, extensions
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_block_spans() {
+        assert_eq!(block_spans(9, 10, None).unwrap(), vec![(9, 10)]);
+        assert_eq!(block_spans(100, 110, None).unwrap(), vec![(100, 110)]);
+        assert_eq!(
+            block_spans(100, 103, Some(1)).unwrap(),
+            vec![(100, 101), (101, 102), (102, 103)]
+        );
+        assert_eq!(
+            block_spans(100, 105, Some(2)).unwrap(),
+            vec![(100, 102), (102, 104), (104, 105)]
+        );
+        assert!(block_spans(100, 100, None).is_err());
+        assert!(block_spans(100, 105, Some(0)).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_verify_fixture() {
+        let dir = std::env::temp_dir().join(format!("opfp-verify-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let op_program = dir.join("op-program");
+        let from_op_program = FromOpProgram::try_parse_from([
+            "opfp",
+            "--collector",
+            "native",
+            "--op-program",
+            op_program.to_str().unwrap(),
+            "--data-dir",
+            dir.to_str().unwrap(),
+            "--l2-block",
+            "1",
+            "--l1-rpc-url",
+            "http://localhost",
+            "--l2-rpc-url",
+            "http://localhost",
+            "--beacon-url",
+            "http://localhost",
+            "--rollup-url",
+            "http://localhost",
+            "--output",
+            "fixture.json",
+        ])
+        .expect("failed to parse");
+        let fixture = FaultProofFixture {
+            expected_status: FaultProofStatus::Invalid,
+            ..FaultProofFixture::default()
+        };
+
+        // Stands in for the op-program host, logging the given line and exiting with 1.
+        let verify = |log: &str| {
+            let script = format!("#!/bin/sh\necho '{}'\nexit 1\n", log);
+            std::fs::write(&op_program, script).unwrap();
+            std::fs::set_permissions(
+                &op_program,
+                std::os::unix::fs::PermissionsExt::from_mode(0o755),
+            )
+            .unwrap();
+            from_op_program.verify_fixture(&fixture)
+        };
+        verify("CRIT Claim is invalid")
+            .await
+            .expect("failed to verify an invalid claim");
+        let error = verify("CRIT Program failed err=\"not found\"")
+            .await
+            .expect_err("verified a run missing a preimage");
+        assert!(error.to_string().contains("missing"), "{}", error);
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_span_output_path() {
+        assert_eq!(
+            span_output_path(Path::new("fixtures/Writer.json.gz"), 100, 101),
+            PathBuf::from("fixtures/Writer-100-101.json.gz")
+        );
+        assert_eq!(
+            span_output_path(Path::new("Writer"), 100, 101),
+            PathBuf::from("Writer-100-101")
+        );
+    }
+}
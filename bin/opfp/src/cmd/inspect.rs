@@ -0,0 +1,406 @@
+//! Inspect Subcommand
+
+use alloy_primitives::{keccak256, BlockHash, BlockNumber, B256};
+use clap::{ArgAction, Parser};
+use color_eyre::Result;
+use fp_test_fixtures::{
+    ChainDefinition, FaultProofFixture, FaultProofStatus, PreimageKeyType, WitnessStats,
+};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+
+use super::run_op_program::load_fixture;
+use super::witness::{trie_list, BlockHeader};
+
+/// CLI arguments for the `inspect` subcommand of `opfp`.
+#[derive(Parser, Clone, Debug)]
+pub struct Inspect {
+    /// Path to the fixture file
+    #[clap(short, long, help = "Path to the fixture file")]
+    pub fixture: PathBuf,
+    /// Print the summary as JSON
+    #[clap(long, help = "Print the summary as JSON")]
+    pub json: bool,
+    /// Verbosity level (0-4)
+    #[arg(long, short, help = "Verbosity level (0-4)", action = ArgAction::Count)]
+    pub v: u8,
+}
+
+impl Inspect {
+    /// Runs the `inspect` subcommand.
+    pub async fn run(&self) -> Result<()> {
+        let fixture = load_fixture(&self.fixture)?;
+        let summary = FixtureSummary::new(&fixture);
+        if self.json {
+            println!("{}", serde_json::to_string_pretty(&summary)?);
+        } else {
+            summary.print();
+        }
+        Ok(())
+    }
+}
+
+/// A summary of the contents of a fault proof fixture.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FixtureSummary {
+    /// The version of the fixture format.
+    pub version: u64,
+    /// The L1 head block hash.
+    pub l1_head: BlockHash,
+    /// The L2 head block hash.
+    pub l2_head: BlockHash,
+    /// The agreed L2 output root.
+    pub l2_output_root: B256,
+    /// The claimed L2 output root.
+    pub l2_claim: B256,
+    /// The L2 block number that the claim is from.
+    pub l2_block_number: BlockNumber,
+    /// The expected status of the fixture.
+    pub expected_status: FaultProofStatus,
+    /// A summary of the chain definition.
+    pub chain: ChainSummary,
+    /// The total number and size of the witnesses.
+    pub witnesses: WitnessStats,
+    /// The number and size of the witnesses of each preimage key type.
+    pub witnesses_by_type: BTreeMap<PreimageKeyType, WitnessStats>,
+    /// The number of witnesses whose keys have an unknown preimage key type.
+    pub unknown_witnesses: usize,
+    /// The witnessed L1 headers ending at the L1 head, in ascending block order.
+    pub l1_blocks: Vec<BlockSummary>,
+    /// The witnessed L2 headers ending at the L2 head, in ascending block order.
+    pub l2_blocks: Vec<BlockSummary>,
+}
+
+/// A summary of the chain definition of a fixture.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase", tag = "type")]
+pub enum ChainSummary {
+    /// A named chain, resolved by the fault proof program.
+    Named {
+        /// The name of the chain.
+        name: String,
+    },
+    /// An unnamed chain with an embedded rollup config and genesis.
+    #[serde(rename_all = "camelCase")]
+    Unnamed {
+        /// The L1 chain ID.
+        l1_chain_id: u64,
+        /// The L2 chain ID.
+        l2_chain_id: u64,
+        /// The L2 block time in seconds.
+        block_time: u64,
+        /// The activation times of the scheduled hardforks, in activation order.
+        hardforks: Vec<(String, u64)>,
+    },
+}
+
+/// A summary of a witnessed block header.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BlockSummary {
+    /// The block number.
+    pub number: BlockNumber,
+    /// The block hash.
+    pub hash: BlockHash,
+    /// The block timestamp.
+    pub timestamp: u64,
+    /// The gas used by the block.
+    pub gas_used: u64,
+    /// The transactions of the block, if they are witnessed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub transactions: Option<Vec<TransactionSummary>>,
+}
+
+/// A summary of a witnessed transaction.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TransactionSummary {
+    /// The transaction hash.
+    pub hash: B256,
+    /// The EIP-2718 transaction type, 0 for legacy transactions.
+    pub tx_type: u8,
+    /// The size of the encoded transaction in bytes.
+    pub size: usize,
+}
+
+impl FixtureSummary {
+    /// Summarizes the given fixture.
+    pub fn new(fixture: &FaultProofFixture) -> Self {
+        let inputs = &fixture.inputs;
+        let witnesses_by_type = fixture.witness_stats();
+        let witnesses = WitnessStats {
+            count: fixture.witness_data.len(),
+            size: fixture.witness_data.values().map(|value| value.len()).sum(),
+        };
+
+        // Any keccak256 preimage which decodes as a block header is a candidate, and the L1
+        // and L2 chains are found by following the parent hashes back from their heads.
+        let headers: HashMap<B256, BlockHeader> = fixture
+            .witnesses_of_type(PreimageKeyType::Keccak256)
+            .filter_map(|(_, value)| {
+                let header = BlockHeader::decode_exact(value).ok()?;
+                Some((keccak256(value), header))
+            })
+            .collect();
+        let l1_blocks = header_chain(&headers, inputs.l1_head)
+            .into_iter()
+            .map(|(hash, header)| BlockSummary::new(hash, header, None))
+            .collect();
+        let l2_blocks = header_chain(&headers, inputs.l2_head)
+            .into_iter()
+            .map(|(hash, header)| BlockSummary::new(hash, header, Some(fixture)))
+            .collect();
+
+        Self {
+            version: fixture.version,
+            l1_head: inputs.l1_head,
+            l2_head: inputs.l2_head,
+            l2_output_root: inputs.l2_output_root,
+            l2_claim: inputs.l2_claim,
+            l2_block_number: inputs.l2_block_number,
+            expected_status: fixture.expected_status,
+            chain: ChainSummary::new(&inputs.chain_definition),
+            witnesses,
+            witnesses_by_type,
+            unknown_witnesses: fixture.unknown_witness_keys().count(),
+            l1_blocks,
+            l2_blocks,
+        }
+    }
+
+    /// Prints the summary in a human-readable format to stdout.
+    pub fn print(&self) {
+        println!("Fixture version:  {}", self.version);
+        println!("Expected status:  {:?}", self.expected_status);
+        println!("L1 head:          {}", self.l1_head);
+        println!("L2 head:          {}", self.l2_head);
+        println!("L2 output root:   {}", self.l2_output_root);
+        println!("L2 claim:         {}", self.l2_claim);
+        println!("L2 block number:  {}", self.l2_block_number);
+
+        match &self.chain {
+            ChainSummary::Named { name } => println!("Chain:            {}", name),
+            ChainSummary::Unnamed {
+                l1_chain_id,
+                l2_chain_id,
+                block_time,
+                hardforks,
+            } => {
+                println!(
+                    "Chain:            unnamed (L1 chain ID {}, L2 chain ID {}, {}s blocks)",
+                    l1_chain_id, l2_chain_id, block_time
+                );
+                for (name, time) in hardforks {
+                    println!("  {:<16}{}", name, time);
+                }
+            }
+        }
+
+        println!(
+            "Witnesses:        {} ({} bytes)",
+            self.witnesses.count, self.witnesses.size
+        );
+        for (key_type, stats) in &self.witnesses_by_type {
+            println!(
+                "  {:<16}{} ({} bytes)",
+                key_type.to_string(),
+                stats.count,
+                stats.size
+            );
+        }
+        if self.unknown_witnesses > 0 {
+            println!("  {:<16}{}", "unknown", self.unknown_witnesses);
+        }
+
+        print_blocks("L1", &self.l1_blocks);
+        print_blocks("L2", &self.l2_blocks);
+    }
+}
+
+impl ChainSummary {
+    /// Summarizes the given chain definition.
+    pub fn new(chain_definition: &ChainDefinition) -> Self {
+        let (rollup_config, _) = match chain_definition {
+            ChainDefinition::Named(name) => return Self::Named { name: name.clone() },
+            ChainDefinition::Unnamed(rollup_config, genesis) => (rollup_config, genesis),
+        };
+
+        let hardforks = [
+            ("regolith", rollup_config.regolith_time),
+            ("canyon", rollup_config.canyon_time),
+            ("delta", rollup_config.delta_time),
+            ("ecotone", rollup_config.ecotone_time),
+            ("fjord", rollup_config.fjord_time),
+            ("granite", rollup_config.granite_time),
+            ("holocene", rollup_config.holocene_time),
+        ]
+        .into_iter()
+        .filter_map(|(name, time)| Some((name.to_string(), time?)))
+        .collect();
+
+        Self::Unnamed {
+            l1_chain_id: rollup_config.l1_chain_id,
+            l2_chain_id: rollup_config.l2_chain_id,
+            block_time: rollup_config.block_time,
+            hardforks,
+        }
+    }
+}
+
+impl BlockSummary {
+    /// Summarizes the given header, decoding its transactions from the witness data of the
+    /// fixture if one is given.
+    fn new(hash: B256, header: &BlockHeader, fixture: Option<&FaultProofFixture>) -> Self {
+        let transactions = fixture.and_then(|fixture| {
+            let transactions = trie_list(&fixture.witness_data, header.transactions_root).ok()?;
+            Some(
+                transactions
+                    .iter()
+                    .map(|tx| TransactionSummary {
+                        hash: keccak256(tx),
+                        tx_type: transaction_type(tx),
+                        size: tx.len(),
+                    })
+                    .collect(),
+            )
+        });
+
+        Self {
+            number: header.number,
+            hash,
+            timestamp: header.timestamp,
+            gas_used: header.gas_used,
+            transactions,
+        }
+    }
+}
+
+/// Returns the type of the given encoded transaction. Typed transactions start with their type
+/// byte, up to 0x7f, and legacy transactions with an RLP list header.
+fn transaction_type(tx: &[u8]) -> u8 {
+    tx.first().copied().filter(|&b| b <= 0x7f).unwrap_or(0)
+}
+
+/// Returns the witnessed headers starting at the given block hash and following the parent
+/// hashes, in ascending block order.
+fn header_chain(headers: &HashMap<B256, BlockHeader>, head: B256) -> Vec<(B256, &BlockHeader)> {
+    let mut chain = Vec::new();
+    let mut hash = head;
+    while let Some(header) = headers.get(&hash) {
+        chain.push((hash, header));
+        if header.number == 0 {
+            break;
+        }
+        hash = header.parent_hash;
+    }
+    chain.reverse();
+    chain
+}
+
+/// Prints the witnessed blocks of a chain in a human-readable format to stdout.
+fn print_blocks(chain: &str, blocks: &[BlockSummary]) {
+    let (Some(first), Some(last)) = (blocks.first(), blocks.last()) else {
+        println!("{} blocks:        none witnessed", chain);
+        return;
+    };
+    println!(
+        "{} blocks:        {}-{} ({} headers)",
+        chain,
+        first.number,
+        last.number,
+        blocks.len()
+    );
+    for block in blocks {
+        match &block.transactions {
+            Some(transactions) => println!(
+                "  {} {} (timestamp {}, gas used {}, {} transactions)",
+                block.number,
+                block.hash,
+                block.timestamp,
+                block.gas_used,
+                transactions.len()
+            ),
+            None => println!(
+                "  {} {} (timestamp {}, gas used {})",
+                block.number, block.hash, block.timestamp, block.gas_used
+            ),
+        }
+        for tx in block.transactions.iter().flatten() {
+            println!("    {} (type {}, {} bytes)", tx.hash, tx.tx_type, tx.size);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cmd::witness::{keccak_key, tests::single_leaf_trie};
+    use alloy_primitives::Bytes;
+
+    #[test]
+    fn test_fixture_summary() {
+        let tx = [0x7e, 0xc0];
+        let (transactions_root, mut witness_data) = single_leaf_trie(&[0x80], &tx);
+
+        let l1_parent = BlockHeader {
+            number: 99,
+            ..Default::default()
+        };
+        let l1_head = BlockHeader {
+            number: 100,
+            parent_hash: l1_parent.hash_slow(),
+            ..Default::default()
+        };
+        let l2_head = BlockHeader {
+            number: 1336,
+            transactions_root,
+            ..Default::default()
+        };
+        for header in [&l1_parent, &l1_head, &l2_head] {
+            witness_data.insert(
+                keccak_key(header.hash_slow()),
+                Bytes::from(alloy_rlp::encode(header)),
+            );
+        }
+
+        let mut fixture = FaultProofFixture {
+            witness_data,
+            ..Default::default()
+        };
+        fixture.inputs.l1_head = l1_head.hash_slow();
+        fixture.inputs.l2_head = l2_head.hash_slow();
+
+        let summary = FixtureSummary::new(&fixture);
+        assert_eq!(summary.witnesses.count, 4);
+        assert_eq!(
+            summary.witnesses_by_type[&PreimageKeyType::Keccak256].count,
+            4
+        );
+        assert_eq!(
+            summary
+                .l1_blocks
+                .iter()
+                .map(|block| block.number)
+                .collect::<Vec<_>>(),
+            vec![99, 100]
+        );
+        assert!(summary.l1_blocks[1].transactions.is_none());
+
+        assert_eq!(summary.l2_blocks.len(), 1);
+        let transactions = summary.l2_blocks[0].transactions.as_ref().unwrap();
+        assert_eq!(transactions.len(), 1);
+        assert_eq!(transactions[0].tx_type, 0x7e);
+        assert_eq!(transactions[0].hash, keccak256(tx));
+    }
+
+    #[test]
+    fn test_transaction_type() {
+        assert_eq!(transaction_type(&[0x02, 0xc0]), 0x02);
+        assert_eq!(transaction_type(&[0x7e, 0xc0]), 0x7e);
+        assert_eq!(transaction_type(&[0x7f, 0xc0]), 0x7f);
+        assert_eq!(transaction_type(&[0xc0]), 0);
+        assert_eq!(transaction_type(&[]), 0);
+    }
+}
@@ -0,0 +1,87 @@
+//! Migrate Subcommand
+
+use clap::{ArgAction, Parser};
+use color_eyre::{eyre::eyre, Result};
+use fp_test_fixtures::{FaultProofFixture, FIXTURE_VERSION};
+use std::path::Path;
+use tracing::{debug, info};
+
+use super::run_suite::discover_fixtures;
+
+/// The logging target to use for [tracing].
+const TARGET: &str = "migrate";
+
+/// CLI arguments for the `migrate` subcommand of `opfp`.
+#[derive(Parser, Clone, Debug)]
+pub struct Migrate {
+    /// Fixture file, directory or glob pattern of the fixture files to migrate
+    #[clap(
+        short,
+        long,
+        help = "Fixture file, directory or glob pattern of the fixture files to migrate"
+    )]
+    pub fixtures: String,
+    /// Only report the fixtures which would be migrated
+    #[clap(long, help = "Only report the fixtures which would be migrated")]
+    pub dry_run: bool,
+    /// Verbosity level (0-4)
+    #[arg(long, short, help = "Verbosity level (0-4)", action = ArgAction::Count)]
+    pub v: u8,
+}
+
+impl Migrate {
+    /// Runs the `migrate` subcommand.
+    pub async fn run(&self) -> Result<()> {
+        let fixtures = discover_fixtures(&self.fixtures)?;
+        if fixtures.is_empty() {
+            return Err(eyre!("No fixtures found matching {}", self.fixtures));
+        }
+
+        let mut migrated = 0;
+        for fixture in &fixtures {
+            if self
+                .migrate_fixture(fixture)
+                .map_err(|e| eyre!("Failed to migrate fixture {:?}: {}", fixture, e))?
+            {
+                migrated += 1;
+            }
+        }
+
+        info!(
+            target: TARGET,
+            "Migrated {} of {} fixtures to version {}",
+            migrated,
+            fixtures.len(),
+            FIXTURE_VERSION
+        );
+
+        Ok(())
+    }
+
+    /// Upgrades the fixture at the given path to the current format version, rewriting it in
+    /// place. Returns whether the fixture needed to be migrated.
+    fn migrate_fixture(&self, path: &Path) -> Result<bool> {
+        let data = std::fs::read(path)?;
+        let (fixture, version) = FaultProofFixture::decode(&data)?;
+        if version == FIXTURE_VERSION {
+            debug!(target: TARGET, "Fixture {:?} is up to date", path);
+            return Ok(false);
+        }
+
+        if self.dry_run {
+            info!(
+                target: TARGET,
+                "Would migrate {:?} from version {} to {}", path, version, FIXTURE_VERSION
+            );
+            return Ok(true);
+        }
+
+        fixture.save(path)?;
+        info!(
+            target: TARGET,
+            "Migrated {:?} from version {} to {}", path, version, FIXTURE_VERSION
+        );
+
+        Ok(true)
+    }
+}
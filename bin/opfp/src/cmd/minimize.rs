@@ -0,0 +1,161 @@
+//! Minimize Subcommand
+
+use alloy_primitives::{Bytes, B256};
+use clap::{ArgAction, Parser};
+use color_eyre::{eyre::eyre, Result};
+use fp_test_fixtures::FaultProofStatus;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use tracing::{info, warn};
+
+use super::report::FixtureResult;
+use super::run_op_program::{load_fixture, PreimageUsage, ProgramArgs};
+
+/// The logging target to use for [tracing].
+const TARGET: &str = "minimize";
+
+/// CLI arguments for the `minimize` subcommand of `opfp`.
+#[derive(Parser, Clone, Debug)]
+pub struct Minimize {
+    /// The fault proof program to run the fixture with
+    #[command(flatten)]
+    pub program: ProgramArgs,
+    /// Path to the fixture file
+    #[clap(short, long, help = "Path to the fixture file")]
+    pub fixture: PathBuf,
+    /// Path to write the minimized fixture to
+    #[clap(long, help = "Path to write the minimized fixture to")]
+    pub output: PathBuf,
+    /// Verbosity level (0-4)
+    #[arg(long, short, help = "Verbosity level (0-4)", action = ArgAction::Count)]
+    pub v: u8,
+}
+
+impl Minimize {
+    /// Runs the `minimize` subcommand.
+    pub async fn run(&self) -> Result<()> {
+        // The output is removed if the minimized fixture fails, so it must not be the input.
+        if self.output == self.fixture {
+            return Err(eyre!("The output must differ from the fixture"));
+        }
+
+        let program = ProgramArgs {
+            track_preimages: true,
+            ..self.program.clone()
+        };
+
+        let result = program.run_fixture_file(&self.fixture).await;
+        let usage = checked_usage(&result)?;
+
+        let mut fixture = load_fixture(&self.fixture)?;
+        let (count, size) = witness_size(&fixture.witness_data);
+        fixture
+            .witness_data
+            .retain(|key, _| !usage.unused.contains(key));
+        let (min_count, min_size) = witness_size(&fixture.witness_data);
+        fixture.save(&self.output)?;
+
+        // Confirm the minimized fixture still reaches its expected status without missing any
+        // preimage, as the preimages requested may depend on the preimages available.
+        let result = program.run_fixture_file(&self.output).await;
+        if let Err(e) = checked_usage(&result) {
+            if let Err(e) = std::fs::remove_file(&self.output) {
+                warn!(target: TARGET, "Failed to remove {:?}: {}", self.output, e);
+            }
+            return Err(eyre!("Minimized fixture failed: {}", e));
+        }
+
+        info!(
+            target: TARGET,
+            "Minimized {:?} into {:?}",
+            self.fixture,
+            self.output
+        );
+        println!(
+            "Kept {} of {} witnesses ({} of {} bytes)",
+            min_count, count, min_size, size
+        );
+
+        Ok(())
+    }
+}
+
+/// Checks that the run reached the expected status of the fixture without missing any
+/// requested preimage, returning the preimage usage of the run.
+fn checked_usage(result: &FixtureResult) -> Result<PreimageUsage> {
+    if let Some(error) = &result.error {
+        return Err(eyre!("Failed to run fixture: {}", error));
+    }
+    let stats = result
+        .stats
+        .as_ref()
+        .ok_or_else(|| eyre!("Missing program stats"))?;
+    if !result.passed {
+        return Err(eyre!(
+            "Fault proof status mismatch: expected {:?}, got {:?}",
+            result.expected_status.unwrap_or(FaultProofStatus::Unknown),
+            stats.status
+        ));
+    }
+    let usage = stats
+        .preimage_usage
+        .clone()
+        .ok_or_else(|| eyre!("Missing preimage usage"))?;
+    if !usage.missing.is_empty() {
+        return Err(eyre!(
+            "Fixture {:?} is missing {} requested preimages",
+            result.fixture,
+            usage.missing.len()
+        ));
+    }
+    Ok(usage)
+}
+
+/// Returns the number of witnesses and the total size of their values in bytes.
+fn witness_size(witness_data: &BTreeMap<B256, Bytes>) -> (usize, usize) {
+    (
+        witness_data.len(),
+        witness_data.values().map(|value| value.len()).sum(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cmd::run_op_program::ProgramStats;
+    use std::path::Path;
+
+    #[test]
+    fn test_checked_usage() {
+        let mut result = FixtureResult::new(Path::new("fixture.json"));
+        result.expected_status = Some(FaultProofStatus::Valid);
+        result.stats = Some(ProgramStats {
+            status: FaultProofStatus::Invalid,
+            preimage_usage: Some(PreimageUsage {
+                requested: 1,
+                ..PreimageUsage::default()
+            }),
+            ..ProgramStats::default()
+        });
+        assert!(checked_usage(&result).is_err());
+
+        result.passed = true;
+        assert_eq!(
+            checked_usage(&result).expect("failed to check").requested,
+            1
+        );
+
+        let usage = result
+            .stats
+            .as_mut()
+            .unwrap()
+            .preimage_usage
+            .as_mut()
+            .unwrap();
+        usage.missing.insert(B256::from([2; 32]));
+        assert!(checked_usage(&result).is_err());
+
+        result.error = Some("failed".to_string());
+        assert!(checked_usage(&result).is_err());
+    }
+}
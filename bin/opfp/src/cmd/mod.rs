@@ -4,9 +4,22 @@ use clap::Parser;
 use color_eyre::eyre::{eyre, Result};
 use tracing::Level;
 
+pub mod collector;
+pub mod diff_run;
 pub mod from_op_program;
+pub mod inspect;
+pub mod migrate;
+pub mod minimize;
+pub mod mutate;
+pub mod progress;
+pub mod report;
 pub mod run_op_program;
+pub mod run_suite;
+pub mod serve_preimages;
 pub mod util;
+pub mod validate;
+pub mod witness;
+pub mod workspace;
 
 /// Main CLI
 #[derive(Parser, Clone, Debug)]
@@ -24,6 +37,22 @@ pub enum Commands {
     FromOpProgram(from_op_program::FromOpProgram),
     /// Runs the op-program implementation with a given fixture.
     RunOpProgram(run_op_program::RunOpProgram),
+    /// Runs the op-program implementation with every fixture in a directory.
+    RunSuite(run_suite::RunSuite),
+    /// Migrates fixtures to the current fixture format version.
+    Migrate(migrate::Migrate),
+    /// Statically validates fixtures without running them.
+    Validate(validate::Validate),
+    /// Prints a summary of the contents of a fixture.
+    Inspect(inspect::Inspect),
+    /// Runs a fixture through every configured fault proof program and reports divergences.
+    DiffRun(diff_run::DiffRun),
+    /// Serves the witness data of a fixture over the preimage oracle protocol.
+    ServePreimages(serve_preimages::ServePreimages),
+    /// Strips the witnesses a fixture does not need to reach its expected status.
+    Minimize(minimize::Minimize),
+    /// Derives adversarial fixtures by mutating the inputs and witness data of a fixture.
+    Mutate(mutate::Mutate),
 }
 
 impl Cli {
@@ -32,6 +61,14 @@ impl Cli {
         match &self.command {
             Commands::FromOpProgram(cmd) => cmd.v,
             Commands::RunOpProgram(cmd) => cmd.v,
+            Commands::RunSuite(cmd) => cmd.v,
+            Commands::Migrate(cmd) => cmd.v,
+            Commands::Validate(cmd) => cmd.v,
+            Commands::Inspect(cmd) => cmd.v,
+            Commands::DiffRun(cmd) => cmd.v,
+            Commands::ServePreimages(cmd) => cmd.v,
+            Commands::Minimize(cmd) => cmd.v,
+            Commands::Mutate(cmd) => cmd.v,
         }
     }
 
@@ -56,6 +93,14 @@ impl Cli {
         match self.command {
             Commands::FromOpProgram(cmd) => cmd.run().await,
             Commands::RunOpProgram(cmd) => cmd.run().await,
+            Commands::RunSuite(cmd) => cmd.run().await,
+            Commands::Migrate(cmd) => cmd.run().await,
+            Commands::Validate(cmd) => cmd.run().await,
+            Commands::Inspect(cmd) => cmd.run().await,
+            Commands::DiffRun(cmd) => cmd.run().await,
+            Commands::ServePreimages(cmd) => cmd.run().await,
+            Commands::Minimize(cmd) => cmd.run().await,
+            Commands::Mutate(cmd) => cmd.run().await,
         }
     }
 }
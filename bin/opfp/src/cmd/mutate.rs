@@ -0,0 +1,375 @@
+//! Mutate Subcommand
+
+use alloy_primitives::B256;
+use clap::{ArgAction, Parser, ValueEnum};
+use color_eyre::{eyre::eyre, Result};
+use fp_test_fixtures::{FaultProofFixture, FaultProofStatus};
+use std::path::{Path, PathBuf};
+use tracing::{info, warn};
+
+use super::run_op_program::{load_fixture, PreimageUsage, ProgramArgs};
+use super::witness::{keccak_key, keccak_preimage, BlockHeader};
+
+/// The logging target to use for [tracing].
+const TARGET: &str = "mutate";
+
+/// The bits of the L2 claim flipped by [Mutation::ClaimBitFlip], counted from the least
+/// significant bit.
+const CLAIM_BITS: [usize; 4] = [0, 1, 128, 255];
+
+/// CLI arguments for the `mutate` subcommand of `opfp`.
+#[derive(Parser, Clone, Debug)]
+pub struct Mutate {
+    /// The fault proof program to run each mutant with, to check its expected status
+    #[command(flatten)]
+    pub program: ProgramArgs,
+    /// Path to the fixture file
+    #[clap(short, long, help = "Path to the fixture file")]
+    pub fixture: PathBuf,
+    /// Directory to write the mutated fixtures to
+    #[clap(long, help = "Directory to write the mutated fixtures to")]
+    pub output_dir: PathBuf,
+    /// The mutations to apply, defaulting to all of them
+    #[clap(
+        long,
+        value_enum,
+        value_delimiter = ',',
+        help = "Comma separated mutations to apply, defaulting to all of them"
+    )]
+    pub mutations: Vec<Mutation>,
+    /// Verbosity level (0-4)
+    #[arg(long, short, help = "Verbosity level (0-4)", action = ArgAction::Count)]
+    pub v: u8,
+}
+
+/// A perturbation of the inputs or witness data of a fixture.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Mutation {
+    /// Flips single bits of the L2 claim.
+    ClaimBitFlip,
+    /// Replaces the L1 head with its parent block, from which the claimed L2 block may not
+    /// be derivable. The mutant is dropped if the claim remains valid.
+    EarlierL1Head,
+    /// Shifts the L2 block number of the claim by one block in each direction.
+    ShiftBlockNumber,
+    /// Removes each of the witnesses which every run requests. Requires a VM to check.
+    RemoveWitness,
+    /// Corrupts each of the witnesses which every run requests. Requires a VM to check.
+    CorruptWitness,
+}
+
+impl Mutation {
+    /// Returns whether the mutation perturbs the witness data rather than the inputs.
+    pub fn is_witness(&self) -> bool {
+        matches!(self, Mutation::RemoveWitness | Mutation::CorruptWitness)
+    }
+
+    /// All mutations, in the order they are applied.
+    pub const ALL: [Mutation; 5] = [
+        Mutation::ClaimBitFlip,
+        Mutation::EarlierL1Head,
+        Mutation::ShiftBlockNumber,
+        Mutation::RemoveWitness,
+        Mutation::CorruptWitness,
+    ];
+}
+
+/// A fixture derived from another by a [Mutation].
+#[derive(Debug)]
+pub struct Mutant {
+    /// The name of the mutant, unique among the mutants of a fixture.
+    pub name: String,
+    /// The mutated fixture, with the expected status of the mutation.
+    pub fixture: FaultProofFixture,
+}
+
+impl Mutate {
+    /// Runs the `mutate` subcommand.
+    pub async fn run(&self) -> Result<()> {
+        let fixture = load_fixture(&self.fixture)?;
+        let mutations = if self.mutations.is_empty() {
+            Mutation::ALL.to_vec()
+        } else {
+            self.mutations.clone()
+        };
+
+        // Mutants are checked with preimage tracking, so that a mutant reaching its status
+        // only because a preimage went missing is dropped.
+        let mut program = self.program.clone();
+        program.track_preimages = true;
+        let vm = program.cannon.is_some() || program.asterisc.is_some();
+
+        std::fs::create_dir_all(&self.output_dir)?;
+        let mut count = 0;
+        let mut dropped = 0;
+        for mutation in mutations {
+            // Natively, the host fails on a missing or corrupt preimage, while the program
+            // only panics on them within a VM.
+            if mutation.is_witness() && !vm {
+                warn!(
+                    target: TARGET,
+                    "Skipping {:?}: witness mutants can only be checked within a VM", mutation
+                );
+                continue;
+            }
+            let mutants = match mutants(&fixture, mutation) {
+                Ok(mutants) => mutants,
+                Err(e) => {
+                    warn!(target: TARGET, "Skipping {:?}: {}", mutation, e);
+                    continue;
+                }
+            };
+            for mutant in mutants {
+                let path = mutant_path(&self.output_dir, &self.fixture, &mutant.name)?;
+                mutant.fixture.save(&path)?;
+
+                // The expected status of a mutant is only predicted from its mutation, so
+                // mutants which do not reach it are dropped.
+                let result = program.run_fixture_file(&path).await;
+                let missing = result
+                    .stats
+                    .as_ref()
+                    .and_then(|stats| stats.preimage_usage.as_ref())
+                    .map_or(0, |usage| unexpected_missing(&fixture, usage));
+                if !result.passed || missing > 0 {
+                    let outcome = match (&result.error, &result.stats) {
+                        (Some(error), _) => error.clone(),
+                        (None, Some(stats)) if missing > 0 => format!(
+                            "status {:?} with {} missing preimages",
+                            stats.status, missing
+                        ),
+                        (None, Some(stats)) => format!("status {:?}", stats.status),
+                        (None, None) => "no status".to_string(),
+                    };
+                    warn!(
+                        target: TARGET,
+                        "Dropping mutant {}: expected status {:?}, got {}",
+                        mutant.name,
+                        mutant.fixture.expected_status,
+                        outcome
+                    );
+                    std::fs::remove_file(&path)?;
+                    dropped += 1;
+                    continue;
+                }
+                println!(
+                    "{} (expected {:?})",
+                    path.display(),
+                    mutant.fixture.expected_status
+                );
+                count += 1;
+            }
+        }
+
+        info!(
+            target: TARGET,
+            "Wrote {} mutants of {:?} to {:?}, dropped {} which did not reach their expected \
+             status",
+            count,
+            self.fixture,
+            self.output_dir,
+            dropped
+        );
+
+        Ok(())
+    }
+}
+
+/// Returns the mutants of the fixture for the given mutation.
+///
+/// Mutated inputs are expected to make the claim [FaultProofStatus::Invalid], while removed
+/// or corrupted witnesses are expected to make the program [FaultProofStatus::Panic] within a
+/// VM. These statuses are predictions, which must be checked by running the mutants.
+pub fn mutants(fixture: &FaultProofFixture, mutation: Mutation) -> Result<Vec<Mutant>> {
+    let mutant =
+        |name: String, status: FaultProofStatus, mutate: &dyn Fn(&mut FaultProofFixture)| {
+            let mut fixture = fixture.clone();
+            mutate(&mut fixture);
+            fixture.expected_status = status;
+            Mutant { name, fixture }
+        };
+
+    let mut mutants = Vec::new();
+    match mutation {
+        Mutation::ClaimBitFlip => {
+            for bit in CLAIM_BITS {
+                mutants.push(mutant(
+                    format!("claim-bit-{}", bit),
+                    FaultProofStatus::Invalid,
+                    &|fixture| fixture.inputs.l2_claim[31 - bit / 8] ^= 1 << (bit % 8),
+                ));
+            }
+        }
+        Mutation::EarlierL1Head => {
+            let header = header(fixture, fixture.inputs.l1_head)?;
+            mutants.push(mutant(
+                "earlier-l1-head".to_string(),
+                FaultProofStatus::Invalid,
+                &|fixture| fixture.inputs.l1_head = header.parent_hash,
+            ));
+        }
+        Mutation::ShiftBlockNumber => {
+            let l2_head = header(fixture, fixture.inputs.l2_head)?;
+            mutants.push(mutant(
+                "block-number-plus-1".to_string(),
+                FaultProofStatus::Invalid,
+                &|fixture| fixture.inputs.l2_block_number += 1,
+            ));
+            // The claimed block must remain after the agreed L2 head.
+            if fixture.inputs.l2_block_number > l2_head.number + 1 {
+                mutants.push(mutant(
+                    "block-number-minus-1".to_string(),
+                    FaultProofStatus::Invalid,
+                    &|fixture| fixture.inputs.l2_block_number -= 1,
+                ));
+            }
+        }
+        Mutation::RemoveWitness => {
+            for (name, key) in required_witnesses(fixture) {
+                mutants.push(mutant(
+                    format!("remove-{}", name),
+                    FaultProofStatus::Panic,
+                    &|fixture| {
+                        fixture.witness_data.remove(&key);
+                    },
+                ));
+            }
+        }
+        Mutation::CorruptWitness => {
+            for (name, key) in required_witnesses(fixture) {
+                mutants.push(mutant(
+                    format!("corrupt-{}", name),
+                    FaultProofStatus::Panic,
+                    &|fixture| {
+                        if let Some(value) = fixture.witness_data.get_mut(&key) {
+                            let mut corrupted = value.to_vec();
+                            if let Some(byte) = corrupted.last_mut() {
+                                *byte ^= 0xff;
+                            }
+                            *value = corrupted.into();
+                        }
+                    },
+                ));
+            }
+        }
+    }
+    Ok(mutants)
+}
+
+/// Returns the witnesses which every run of the fixture requests, by name: the headers of
+/// the L1 and L2 heads and the preimage of the agreed output root.
+fn required_witnesses(fixture: &FaultProofFixture) -> Vec<(&'static str, B256)> {
+    [
+        ("l1-head-header", fixture.inputs.l1_head),
+        ("l2-head-header", fixture.inputs.l2_head),
+        ("agreed-output", fixture.inputs.l2_output_root),
+    ]
+    .into_iter()
+    .map(|(name, hash)| (name, keccak_key(hash)))
+    .filter(|(_, key)| fixture.witness_data.contains_key(key))
+    .collect()
+}
+
+/// Returns the number of preimages missing from a run of a mutant which are not in the
+/// witness data of the original fixture, i.e. which were not removed by the mutation.
+fn unexpected_missing(original: &FaultProofFixture, usage: &PreimageUsage) -> usize {
+    usage
+        .missing
+        .iter()
+        .filter(|key| !original.witness_data.contains_key(*key))
+        .count()
+}
+
+/// Returns the decoded header of the block with the given hash from the witness data.
+fn header(fixture: &FaultProofFixture, hash: B256) -> Result<BlockHeader> {
+    let header = keccak_preimage(&fixture.witness_data, hash)
+        .ok_or_else(|| eyre!("Missing header of block {}", hash))?;
+    BlockHeader::decode_exact(header)
+}
+
+/// Returns the path of the named mutant of the fixture at the given path, keeping the
+/// extensions of the fixture file.
+fn mutant_path(output_dir: &Path, fixture: &Path, name: &str) -> Result<PathBuf> {
+    let file_name = fixture
+        .file_name()
+        .and_then(|name| name.to_str())
+        .ok_or_else(|| eyre!("Invalid fixture path {:?}", fixture))?;
+    let (stem, extension) = file_name.split_at(file_name.find('.').unwrap_or(file_name.len()));
+    Ok(output_dir.join(format!("{}-{}{}", stem, name, extension)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy_primitives::{keccak256, Bytes};
+    use alloy_rlp::Encodable;
+
+    fn fixture() -> FaultProofFixture {
+        let l2_head = BlockHeader {
+            number: 10,
+            ..BlockHeader::default()
+        };
+        let mut encoded = Vec::new();
+        l2_head.encode(&mut encoded);
+
+        let mut fixture = FaultProofFixture::default();
+        fixture.inputs.l2_head = keccak256(&encoded);
+        fixture.inputs.l2_block_number = 12;
+        fixture
+            .witness_data
+            .insert(keccak_key(fixture.inputs.l2_head), Bytes::from(encoded));
+        fixture
+    }
+
+    #[test]
+    fn test_mutants() {
+        let fixture = fixture();
+
+        let claims = mutants(&fixture, Mutation::ClaimBitFlip).expect("failed to mutate");
+        assert_eq!(claims.len(), CLAIM_BITS.len());
+        assert_eq!(claims[0].fixture.inputs.l2_claim[31], 1);
+        assert_eq!(claims[3].fixture.inputs.l2_claim[0], 0x80);
+        assert!(claims
+            .iter()
+            .all(|m| m.fixture.expected_status == FaultProofStatus::Invalid));
+
+        let shifted = mutants(&fixture, Mutation::ShiftBlockNumber).expect("failed to mutate");
+        assert_eq!(
+            shifted
+                .iter()
+                .map(|m| m.fixture.inputs.l2_block_number)
+                .collect::<Vec<_>>(),
+            vec![13, 11]
+        );
+
+        assert!(mutants(&fixture, Mutation::EarlierL1Head).is_err());
+
+        let removed = mutants(&fixture, Mutation::RemoveWitness).expect("failed to mutate");
+        assert_eq!(removed.len(), 1);
+        assert_eq!(removed[0].name, "remove-l2-head-header");
+        assert!(removed[0].fixture.witness_data.is_empty());
+        assert_eq!(removed[0].fixture.expected_status, FaultProofStatus::Panic);
+
+        let removed_key = keccak_key(fixture.inputs.l2_head);
+        let mut usage = PreimageUsage {
+            missing: [removed_key].into(),
+            ..PreimageUsage::default()
+        };
+        assert_eq!(unexpected_missing(&fixture, &usage), 0);
+        usage.missing.insert(B256::from([2; 32]));
+        assert_eq!(unexpected_missing(&fixture, &usage), 1);
+    }
+
+    #[test]
+    fn test_mutant_path() {
+        assert_eq!(
+            mutant_path(
+                Path::new("mutants"),
+                Path::new("fixtures/Reader-2000000.json.gz"),
+                "claim-bit-0"
+            )
+            .expect("failed to build path"),
+            PathBuf::from("mutants/Reader-2000000-claim-bit-0.json.gz")
+        );
+    }
+}
@@ -0,0 +1,218 @@
+//! Helpers for parsing the periodic progress logs of Cannon runs.
+
+use color_eyre::Result;
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::path::Path;
+
+/// The message of the progress logs Cannon writes at its `--info-at` interval.
+const PROGRESS_MESSAGE: &str = "processing";
+
+/// The columns of the CSV export of a progress time series.
+const CSV_HEADER: &str = "step,pc,ips,pages,memory,name,num_preimage_requests,total_preimage_size";
+
+/// The progress of a Cannon run at a single step, as logged at its `--info-at` interval.
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+pub struct ProgressSample {
+    /// The step of the VM.
+    pub step: u64,
+    /// The program counter of the VM.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pc: Option<u64>,
+    /// The instructions executed per second since the start of the run.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ips: Option<f64>,
+    /// The number of memory pages allocated.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pages: Option<u64>,
+    /// The memory allocated, formatted by Cannon, e.g. `4.8 MiB`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub memory: Option<String>,
+    /// The symbol of the program counter, from the Cannon metadata.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// The number of preimages requested so far, if logged.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub num_preimage_requests: Option<u64>,
+    /// The total size of the preimages requested so far, if logged.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub total_preimage_size: Option<u64>,
+}
+
+impl ProgressSample {
+    /// Parses a progress log line, in either the terminal or logfmt format of the Cannon
+    /// logger. Returns [None] for any other line.
+    pub fn parse(line: &str) -> Option<Self> {
+        let fields = log_fields(line);
+        let is_progress = fields.iter().any(|(key, value)| match key {
+            Some(key) => *key == "msg" && value == PROGRESS_MESSAGE,
+            None => value == PROGRESS_MESSAGE,
+        });
+        if !is_progress {
+            return None;
+        }
+
+        let field = |name: &str| {
+            fields
+                .iter()
+                .find(|(key, _)| *key == Some(name))
+                .map(|(_, value)| value.as_str())
+        };
+        // The terminal format groups the digits of large integers with commas.
+        let integer = |name: &str| field(name)?.replace(',', "").parse::<u64>().ok();
+
+        Some(Self {
+            step: integer("step")?,
+            pc: field("pc").and_then(|pc| {
+                u64::from_str_radix(pc.trim_start_matches("0x").trim_start_matches("0X"), 16).ok()
+            }),
+            ips: field("ips").and_then(|ips| ips.replace(',', "").parse().ok()),
+            pages: integer("pages"),
+            memory: field("mem").map(str::to_string),
+            name: field("name").map(str::to_string),
+            num_preimage_requests: integer("num_preimage_requests"),
+            total_preimage_size: integer("total_preimage_size"),
+        })
+    }
+}
+
+/// Parses the progress time series from the output of a Cannon run, skipping every other
+/// line of output.
+pub fn parse_progress(output: &str) -> Vec<ProgressSample> {
+    output.lines().filter_map(ProgressSample::parse).collect()
+}
+
+/// Writes the progress time series to a CSV file, one row per sample. Missing values are
+/// left empty.
+pub fn write_progress_csv(samples: &[ProgressSample], path: &Path) -> Result<()> {
+    let mut file = std::io::BufWriter::new(std::fs::File::create(path)?);
+    writeln!(file, "{}", CSV_HEADER)?;
+    for sample in samples {
+        let cell = |value: Option<String>| value.map(|v| csv_escape(&v)).unwrap_or_default();
+        writeln!(
+            file,
+            "{},{},{},{},{},{},{},{}",
+            sample.step,
+            cell(sample.pc.map(|pc| format!("{:#x}", pc))),
+            cell(sample.ips.map(|ips| ips.to_string())),
+            cell(sample.pages.map(|pages| pages.to_string())),
+            cell(sample.memory.clone()),
+            cell(sample.name.clone()),
+            cell(sample.num_preimage_requests.map(|n| n.to_string())),
+            cell(sample.total_preimage_size.map(|n| n.to_string())),
+        )?;
+    }
+    file.flush()?;
+    Ok(())
+}
+
+/// Quotes a CSV value if it contains a delimiter, quote or line break.
+fn csv_escape(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Splits a log line into its fields: `key=value` pairs, with optionally quoted values, and
+/// bare words without a key.
+fn log_fields(line: &str) -> Vec<(Option<&str>, String)> {
+    let mut fields = Vec::new();
+    let mut rest = line.trim();
+    while !rest.is_empty() {
+        let token_end = rest.find([' ', '=']).unwrap_or(rest.len());
+        let (key, after_key) = if rest[token_end..].starts_with('=') {
+            (Some(&rest[..token_end]), &rest[token_end + 1..])
+        } else {
+            (None, rest)
+        };
+
+        let (value, remaining) = match after_key.strip_prefix('"') {
+            Some(quoted) => parse_quoted(quoted),
+            None => {
+                let end = after_key.find(' ').unwrap_or(after_key.len());
+                (after_key[..end].to_string(), &after_key[end..])
+            }
+        };
+        fields.push((key, value));
+        rest = remaining.trim_start();
+    }
+    fields
+}
+
+/// Parses a quoted value up to its closing quote, unescaping any escaped characters. Returns
+/// the value and the remainder of the line.
+fn parse_quoted(quoted: &str) -> (String, &str) {
+    let mut value = String::new();
+    let mut chars = quoted.char_indices();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '"' => return (value, &quoted[i + 1..]),
+            '\\' => {
+                if let Some((_, escaped)) = chars.next() {
+                    value.push(escaped);
+                }
+            }
+            c => value.push(c),
+        }
+    }
+    (value, "")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_progress() {
+        let output = r#"INFO [10-16|12:00:00.000] Loaded input state                       version=2
+INFO [10-16|12:00:01.000] processing                               step=10,000,000 pc=0x0004c1d8 insn=0x8fbf0014 ips=9.5e+06 pages=2,048 mem="8.0 MiB" name=runtime.mallocgc
+t=2024-10-16T12:00:02+0000 lvl=info msg=processing step=20000000 pc=0x0004c1dc ips=9800000.5 pages=4096 mem="16.0 MiB" name="main.(*Foo).bar" num_preimage_requests=12 total_preimage_size=4096
+client output mentioning processing without a step
+"#;
+
+        let samples = parse_progress(output);
+        assert_eq!(
+            samples,
+            vec![
+                ProgressSample {
+                    step: 10_000_000,
+                    pc: Some(0x4c1d8),
+                    ips: Some(9.5e6),
+                    pages: Some(2048),
+                    memory: Some("8.0 MiB".to_string()),
+                    name: Some("runtime.mallocgc".to_string()),
+                    ..ProgressSample::default()
+                },
+                ProgressSample {
+                    step: 20_000_000,
+                    pc: Some(0x4c1dc),
+                    ips: Some(9800000.5),
+                    pages: Some(4096),
+                    memory: Some("16.0 MiB".to_string()),
+                    name: Some("main.(*Foo).bar".to_string()),
+                    num_preimage_requests: Some(12),
+                    total_preimage_size: Some(4096),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_write_progress_csv() {
+        let samples = vec![ProgressSample {
+            step: 10,
+            pc: Some(0x10),
+            pages: Some(2),
+            name: Some("a,b".to_string()),
+            ..ProgressSample::default()
+        }];
+        let path = std::env::temp_dir().join(format!("opfp-progress-{}.csv", std::process::id()));
+        write_progress_csv(&samples, &path).expect("failed to write csv");
+
+        let csv = std::fs::read_to_string(&path).expect("failed to read csv");
+        std::fs::remove_file(&path).expect("failed to remove csv");
+        assert_eq!(csv, format!("{}\n10,0x10,,2,,\"a,b\",,\n", CSV_HEADER));
+    }
+}
@@ -0,0 +1,261 @@
+//! Structured reports for fixture runs.
+
+use alloy_primitives::{BlockNumber, B256};
+use clap::Parser;
+use color_eyre::Result;
+use fp_test_fixtures::{FaultProofFixture, FaultProofStatus};
+use serde::{Deserialize, Serialize};
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+
+use super::run_op_program::ProgramStats;
+
+/// CLI arguments for the structured reports written after running fixtures.
+#[derive(Parser, Clone, Debug)]
+pub struct ReportArgs {
+    /// Optional JSON report file path
+    #[clap(long, help = "Path to the JSON report file")]
+    pub report: Option<PathBuf>,
+    /// Optional JUnit XML report file path
+    #[clap(long, help = "Path to the JUnit XML report file")]
+    pub junit: Option<PathBuf>,
+}
+
+impl ReportArgs {
+    /// Writes the given report in each of the requested formats, using `name` as the test
+    /// suite name.
+    pub fn write(&self, report: &Report, name: &str) -> Result<()> {
+        if let Some(path) = &self.report {
+            let file = std::fs::File::create(path)?;
+            serde_json::to_writer_pretty(file, report)?;
+        }
+        if let Some(path) = &self.junit {
+            std::fs::write(path, report.to_junit(name))?;
+        }
+        Ok(())
+    }
+}
+
+/// The consolidated results of running one or more fixtures.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Report {
+    /// The number of fixtures which exited with their expected status.
+    pub passed: usize,
+    /// The number of fixtures which failed to run or exited with an unexpected status.
+    pub failed: usize,
+    /// The per-fixture results.
+    pub results: Vec<FixtureResult>,
+}
+
+/// The result of running a single fixture.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FixtureResult {
+    /// The path to the fixture file.
+    pub fixture: PathBuf,
+    /// Whether the program exited with the expected status.
+    pub passed: bool,
+    /// A summary of the fixture inputs, if it could be loaded.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub inputs: Option<InputsSummary>,
+    /// The expected status of the fixture, if it could be loaded.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expected_status: Option<FaultProofStatus>,
+    /// The stats of the program run, if it completed.
+    #[serde(flatten)]
+    pub stats: Option<ProgramStats>,
+    /// The error that prevented the fixture from running, if any.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// A summary of the inputs of a fault proof fixture.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InputsSummary {
+    /// The L2 block number that the claim is from.
+    pub l2_block_number: BlockNumber,
+    /// The claimed L2 output root.
+    pub l2_claim: B256,
+}
+
+impl From<&FaultProofFixture> for InputsSummary {
+    fn from(fixture: &FaultProofFixture) -> Self {
+        Self {
+            l2_block_number: fixture.inputs.l2_block_number,
+            l2_claim: fixture.inputs.l2_claim,
+        }
+    }
+}
+
+impl FixtureResult {
+    /// Creates a new, failed [FixtureResult] for the fixture at the given path.
+    pub fn new(fixture: &Path) -> Self {
+        Self {
+            fixture: fixture.to_path_buf(),
+            passed: false,
+            inputs: None,
+            expected_status: None,
+            stats: None,
+            error: None,
+        }
+    }
+
+    /// Returns the runtime of the program in milliseconds, if it completed.
+    pub fn runtime(&self) -> u128 {
+        self.stats.as_ref().map_or(0, |stats| stats.runtime)
+    }
+}
+
+impl Report {
+    /// Creates a new [Report] from the given fixture results.
+    pub fn new(results: Vec<FixtureResult>) -> Self {
+        let passed = results.iter().filter(|r| r.passed).count();
+        Self {
+            passed,
+            failed: results.len() - passed,
+            results,
+        }
+    }
+
+    /// Returns the report encoded as a JUnit XML document, with one test case per fixture.
+    pub fn to_junit(&self, name: &str) -> String {
+        let errors = self.results.iter().filter(|r| r.error.is_some()).count();
+        let time = seconds(self.results.iter().map(FixtureResult::runtime).sum());
+
+        let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+        let _ = writeln!(
+            xml,
+            "<testsuites name=\"{}\" tests=\"{}\" failures=\"{}\" errors=\"{}\" time=\"{}\">",
+            escape_xml(name),
+            self.results.len(),
+            self.failed - errors,
+            errors,
+            time
+        );
+        let _ = writeln!(
+            xml,
+            "  <testsuite name=\"{}\" tests=\"{}\" failures=\"{}\" errors=\"{}\" time=\"{}\">",
+            escape_xml(name),
+            self.results.len(),
+            self.failed - errors,
+            errors,
+            time
+        );
+        for result in &self.results {
+            let classname = result
+                .fixture
+                .parent()
+                .map(|parent| parent.display().to_string())
+                .unwrap_or_default();
+            let _ = write!(
+                xml,
+                "    <testcase name=\"{}\" classname=\"{}\" time=\"{}\"",
+                escape_xml(&result.fixture.display().to_string()),
+                escape_xml(&classname),
+                seconds(result.runtime())
+            );
+
+            let mut body = String::new();
+            if let Some(error) = &result.error {
+                let _ = writeln!(body, "      <error message=\"{}\"/>", escape_xml(error));
+            } else if !result.passed {
+                let actual = result.stats.as_ref().map(|stats| stats.status);
+                let _ = writeln!(
+                    body,
+                    "      <failure message=\"expected status {:?}, got {:?}\"/>",
+                    result.expected_status.unwrap_or(FaultProofStatus::Unknown),
+                    actual.unwrap_or(FaultProofStatus::Unknown)
+                );
+            }
+            if let Some(stats) = &result.stats {
+                if let Ok(stats) = serde_json::to_string(stats) {
+                    let _ = writeln!(
+                        body,
+                        "      <system-out>{}</system-out>",
+                        escape_xml(&stats)
+                    );
+                }
+            }
+
+            if body.is_empty() {
+                xml.push_str("/>\n");
+            } else {
+                let _ = write!(xml, ">\n{}    </testcase>\n", body);
+            }
+        }
+        xml.push_str("  </testsuite>\n</testsuites>\n");
+        xml
+    }
+}
+
+/// Formats a duration in milliseconds as fractional seconds.
+fn seconds(millis: u128) -> String {
+    format!("{}.{:03}", millis / 1000, millis % 1000)
+}
+
+/// Escapes the XML special characters in the given string.
+fn escape_xml(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_escape_xml() {
+        assert_eq!(
+            escape_xml("<a href=\"x\">'&'</a>"),
+            "&lt;a href=&quot;x&quot;&gt;&apos;&amp;&apos;&lt;/a&gt;"
+        );
+    }
+
+    #[test]
+    fn test_report_to_junit() {
+        let mut passed = FixtureResult::new(Path::new("fixtures/Writer-1000000.json"));
+        passed.passed = true;
+        passed.expected_status = Some(FaultProofStatus::Valid);
+        passed.stats = Some(ProgramStats {
+            status: FaultProofStatus::Valid,
+            runtime: 1500,
+            ..Default::default()
+        });
+
+        let mut failed = FixtureResult::new(Path::new("fixtures/Reader-1000000.json"));
+        failed.expected_status = Some(FaultProofStatus::Valid);
+        failed.stats = Some(ProgramStats {
+            status: FaultProofStatus::Panic,
+            runtime: 250,
+            ..Default::default()
+        });
+
+        let mut errored = FixtureResult::new(Path::new("fixtures/missing.json"));
+        errored.error = Some("Failed to read fixture file".to_string());
+
+        let report = Report::new(vec![passed, failed, errored]);
+        assert_eq!(report.passed, 1);
+        assert_eq!(report.failed, 2);
+
+        let junit = report.to_junit("op-program");
+        assert!(junit.contains(
+            "<testsuite name=\"op-program\" tests=\"3\" failures=\"1\" errors=\"1\" time=\"1.750\">"
+        ));
+        assert!(junit.contains(
+            "<testcase name=\"fixtures/Writer-1000000.json\" classname=\"fixtures\" time=\"1.500\">"
+        ));
+        assert!(junit.contains("<failure message=\"expected status Valid, got Panic\"/>"));
+        assert!(junit.contains(
+            "<testcase name=\"fixtures/missing.json\" classname=\"fixtures\" time=\"0.000\">\n      <error message=\"Failed to read fixture file\"/>\n    </testcase>"
+        ));
+    }
+}
@@ -1,31 +1,92 @@
 //! Run Op Program Subcommand
 
 use alloy_primitives::hex::ToHexExt;
-use alloy_primitives::U64;
-use clap::{ArgAction, Parser};
+use alloy_primitives::{B256, U64};
+use clap::{ArgAction, Parser, ValueEnum};
 use color_eyre::eyre::eyre;
 use color_eyre::Result;
-use fp_test_fixtures::{ChainDefinition, FaultProofFixture};
+use fp_test_fixtures::{ChainDefinition, FaultProofFixture, FaultProofStatus, PreimageKeyType};
 use serde::{Deserialize, Serialize};
-use std::process::Command;
-use std::time::{SystemTime, UNIX_EPOCH};
-use std::{env, path::PathBuf};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::FileTimes;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::process::{ExitStatus, Stdio};
+use std::time::{Duration, UNIX_EPOCH};
+use tokio::process::Command;
 use tracing::{debug, error, info, trace, warn};
 
-use super::util::{RollupConfig, VersionedState};
+use super::progress::{parse_progress, write_progress_csv, ProgressSample};
+use super::report::{FixtureResult, InputsSummary, Report, ReportArgs};
+use super::util::{native_status, status_from_exit_code, RollupConfig, VersionedState};
+use super::workspace::WorkspaceArgs;
 
 /// The logging target to use for [tracing].
 const TARGET: &str = "run-op-program";
 
+/// The prefix of the proof files written by Cannon, followed by the step.
+const PROOF_PREFIX: &str = "proof-";
+/// The suffix of the proof files written by Cannon.
+const PROOF_SUFFIX: &str = ".json";
+/// The prefix of the snapshot files written by Cannon, followed by the step.
+const SNAPSHOT_PREFIX: &str = "state-";
+/// The suffix of the snapshot files written by Cannon.
+const SNAPSHOT_SUFFIX: &str = ".bin.gz";
+/// The size of a memory page of the VM, in bytes.
+const PAGE_SIZE: u64 = 4096;
+/// The file read to check that access times are updated in the data directory.
+const ATIME_PROBE_FILE: &str = "atime-probe.txt";
+
 /// CLI arguments for the `run-op-program` subcommand of `opfp`.
 #[derive(Parser, Clone, Debug)]
 pub struct RunOpProgram {
-    /// Path to the op-program binary
-    #[clap(short, long, help = "Path to the op-program binary")]
-    pub op_program: PathBuf,
+    /// The fault proof program to run the fixture with
+    #[command(flatten)]
+    pub program: ProgramArgs,
     /// Path to the fixture file
     #[clap(short, long, help = "Path to the fixture file")]
     pub fixture: PathBuf,
+    /// Optional output file path
+    #[clap(long, help = "Path to the output file")]
+    pub output: Option<PathBuf>,
+    /// Optional CSV file path for the progress of a Cannon run
+    #[clap(
+        long,
+        help = "Path to a CSV file of the progress logged by Cannon at each --info-at step"
+    )]
+    pub progress_csv: Option<PathBuf>,
+    /// The structured reports to write
+    #[command(flatten)]
+    pub report: ReportArgs,
+    /// Verbosity level (0-4)
+    #[arg(long, short, help = "Verbosity level (0-4)", action = ArgAction::Count)]
+    pub v: u8,
+}
+
+/// CLI arguments for the fault proof program used to run fixtures.
+#[derive(Parser, Clone, Debug)]
+pub struct ProgramArgs {
+    /// Path to the op-program binary, also used as the preimage host of other programs
+    #[clap(
+        short,
+        long,
+        help = "Path to the op-program binary, also used as the preimage host of other programs"
+    )]
+    pub op_program: PathBuf,
+    /// The fault proof program implementation to run
+    #[clap(
+        long,
+        value_enum,
+        default_value_t = Program::OpProgram,
+        help = "Fault proof program implementation to run"
+    )]
+    pub program: Program,
+    /// Optional path to the kona-client binary, run natively by the op-program host
+    #[clap(
+        long,
+        help = "Path to the kona-client binary, run natively by the op-program host"
+    )]
+    pub kona_client: Option<PathBuf>,
     /// Optional path to the cannon binary
     #[clap(short, long, help = "Path to the cannon binary")]
     pub cannon: Option<PathBuf>,
@@ -35,16 +96,86 @@ pub struct RunOpProgram {
     /// Optional cannon metadata
     #[clap(long, help = "Path to the cannon metadata")]
     pub cannon_meta: Option<PathBuf>,
-    /// Optional output file path
-    #[clap(long, help = "Path to the output file")]
-    pub output: Option<PathBuf>,
-    /// Verbosity level (0-4)
-    #[arg(long, short, help = "Verbosity level (0-4)", action = ArgAction::Count)]
-    pub v: u8,
+    /// Optional path to the asterisc binary
+    #[clap(long, conflicts_with = "cannon", help = "Path to the asterisc binary")]
+    pub asterisc: Option<PathBuf>,
+    /// Optional asterisc state
+    #[clap(long, help = "Path to the asterisc state")]
+    pub asterisc_state: Option<PathBuf>,
+    /// Whether to serve the preimages from opfp rather than the op-program host within a VM
+    #[clap(
+        long,
+        help = "Serve the preimages from opfp rather than the op-program host within a VM"
+    )]
+    pub preimage_server: bool,
+    /// Whether to record the preimages requested by the program
+    #[clap(
+        long,
+        help = "Record the preimages requested by the program and report the missing and unused \
+                witness entries. Relies on file access times unless the preimage server is used, \
+                failing if the data directory does not update them"
+    )]
+    pub track_preimages: bool,
+    /// Optional wall clock timeout for each program run, in seconds
+    #[clap(long, help = "Wall clock timeout for each program run, in seconds")]
+    pub timeout: Option<u64>,
+    /// Optional maximum number of steps to run the VM for
+    #[clap(long, help = "Maximum number of steps to run the VM for")]
+    pub max_steps: Option<u64>,
+    /// The steps at which the VM logs its progress
+    #[clap(
+        long,
+        default_value = "%10000000",
+        value_parser = parse_step_pattern,
+        help = "Steps at which the VM logs its progress: never, always, =<step> or %<interval>"
+    )]
+    pub info_at: String,
+    /// Optional steps at which Cannon writes a proof into the data directory
+    #[clap(
+        long,
+        value_parser = parse_step_pattern,
+        help = "Steps at which Cannon writes a proof into the data directory: never, always, \
+                =<step> or %<interval>"
+    )]
+    pub proof_at: Option<String>,
+    /// Optional steps at which Cannon writes a state snapshot into the data directory
+    #[clap(
+        long,
+        value_parser = parse_step_pattern,
+        help = "Steps at which Cannon writes a state snapshot into the data directory, which are \
+                summarized in the program stats: never, always, =<step> or %<interval>"
+    )]
+    pub snapshot_at: Option<String>,
+    /// The data directories of the program runs
+    #[command(flatten)]
+    pub workspace: WorkspaceArgs,
+}
+
+/// The fault proof program implementations which fixtures can be run with.
+///
+/// Every program is served its preimages by the op-program host, either natively or from
+/// within a VM whose state was built from the program.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Program {
+    /// The Go op-program client.
+    OpProgram,
+    /// The Rust kona-client.
+    Kona,
+}
+
+/// A runner of a fault proof program, which runs a single fixture and reports the outcome.
+pub trait FaultProofRunner {
+    /// Writes the fixture inputs and witness data where the program expects them.
+    fn prepare(&self) -> impl Future<Output = Result<()>> + Send;
+
+    /// Runs the program to completion, or until it times out.
+    fn run(&self) -> impl Future<Output = Result<ProgramStats>> + Send;
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct ProgramStats {
+    pub status: FaultProofStatus,
     pub runtime: u128,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub instructions: Option<u64>,
@@ -56,6 +187,75 @@ pub struct ProgramStats {
     pub num_preimage_requests: Option<u64>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub total_preimage_size: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub preimage_usage: Option<PreimageUsage>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub checkpoints: Option<Vec<Checkpoint>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub progress: Option<Vec<ProgressSample>>,
+}
+
+/// The VM state at a step of a Cannon run, from the proofs and snapshots written during the
+/// run.
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq, Eq)]
+pub struct Checkpoint {
+    /// The step of the checkpoint.
+    pub step: u64,
+    /// The number of memory pages allocated, if a snapshot was written.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pages: Option<u64>,
+    /// The memory allocated in bytes, if a snapshot was written.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub memory_used: Option<u64>,
+    /// Whether a proof was written.
+    pub proof: bool,
+}
+
+impl Checkpoint {
+    /// Creates a new [Checkpoint] at the given step, without a proof or snapshot.
+    pub fn new(step: u64) -> Self {
+        Self {
+            step,
+            ..Self::default()
+        }
+    }
+}
+
+/// The preimages requested by a program run, compared against the witness data of the
+/// fixture.
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq, Eq)]
+pub struct PreimageUsage {
+    /// The number of distinct preimages requested, including local preimages.
+    pub requested: usize,
+    /// The requested global keys which are missing from the witness data.
+    pub missing: BTreeSet<B256>,
+    /// The witness data keys which were never requested.
+    pub unused: BTreeSet<B256>,
+}
+
+impl PreimageUsage {
+    /// Creates a new [PreimageUsage] from the keys requested by a run of the given fixture.
+    pub fn new(fixture: &FaultProofFixture, requested: &BTreeSet<B256>) -> Self {
+        let missing = requested
+            .iter()
+            .filter(|key| {
+                key[0] != u8::from(PreimageKeyType::Local)
+                    && !fixture.witness_data.contains_key(*key)
+            })
+            .copied()
+            .collect();
+        let unused = fixture
+            .witness_data
+            .keys()
+            .filter(|key| !requested.contains(*key))
+            .copied()
+            .collect();
+        Self {
+            requested: requested.len(),
+            missing,
+            unused,
+        }
+    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -74,27 +274,189 @@ struct CannonDebug {
 impl RunOpProgram {
     /// Runs the `run-op-program` subcommand.
     pub async fn run(&self) -> Result<()> {
-        let fixture = std::fs::read_to_string(&self.fixture)
-            .map_err(|e| eyre!("Failed to read fixture file: {}", e))?;
-        let fixture: FaultProofFixture = serde_json::from_str(&fixture)
-            .map_err(|e| eyre!("Failed to parse fixture file: {}", e))?;
-
-        let dirname = SystemTime::now()
-            .duration_since(UNIX_EPOCH)?
-            .as_millis()
-            .to_string();
-        let data_dir = env::temp_dir().join("run-op-program").join(dirname);
-        if data_dir.exists() {
-            std::fs::remove_dir_all(&data_dir)?;
-        }
-        std::fs::create_dir_all(&data_dir)?;
-
-        let op_program_command =
-            OpProgramCommand::new(self.op_program.clone(), fixture, data_dir.clone());
-
-        match self.cannon.as_ref() {
-            Some(cannon) => {
-                let cannon_command = CannonCommand::new(
+        let result = self.program.run_fixture_file(&self.fixture).await;
+
+        if let (Some(output), Some(stats)) = (&self.output, &result.stats) {
+            let file = std::fs::File::create(output)?;
+            serde_json::to_writer_pretty(file, stats)?;
+        }
+        if let Some(path) = &self.progress_csv {
+            match result
+                .stats
+                .as_ref()
+                .and_then(|stats| stats.progress.as_ref())
+            {
+                Some(progress) => write_progress_csv(progress, path)?,
+                None => warn!(target: TARGET, "No Cannon progress to write to {:?}", path),
+            }
+        }
+
+        let report = Report::new(vec![result]);
+        self.report.write(&report, self.program.name())?;
+
+        let result = &report.results[0];
+        if let Some(error) = &result.error {
+            return Err(eyre!("Failed to run fixture: {}", error));
+        }
+        if !result.passed {
+            return Err(eyre!(
+                "Fault proof status mismatch: expected {:?}, got {:?}",
+                result.expected_status.unwrap_or(FaultProofStatus::Unknown),
+                result
+                    .stats
+                    .as_ref()
+                    .map_or(FaultProofStatus::Unknown, |stats| stats.status)
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+impl ProgramArgs {
+    /// Returns the name of the configured program. Asterisc only runs kona, see
+    /// [ProgramArgs::validate].
+    pub fn name(&self) -> &'static str {
+        match (self.program, self.cannon.is_some(), self.asterisc.is_some()) {
+            (_, false, true) => "kona-asterisc",
+            (Program::OpProgram, true, _) => "cannon",
+            (Program::OpProgram, false, false) => "op-program",
+            (Program::Kona, true, _) => "kona-cannon",
+            (Program::Kona, false, false) => "kona",
+        }
+    }
+
+    /// Checks that the program flags form a supported combination.
+    pub fn validate(&self) -> Result<()> {
+        let vm = self.cannon.is_some() || self.asterisc.is_some();
+        if self.asterisc.is_some() && self.program == Program::OpProgram {
+            return Err(eyre!("The op-program cannot be run with asterisc"));
+        }
+        if !vm && self.program == Program::Kona && self.kona_client.is_none() {
+            return Err(eyre!("Missing kona-client binary"));
+        }
+        if !vm && self.preimage_server {
+            return Err(eyre!("The preimage server can only be used within a VM"));
+        }
+        if self.cannon.is_none() && (self.proof_at.is_some() || self.snapshot_at.is_some()) {
+            return Err(eyre!("Proofs and snapshots can only be written by cannon"));
+        }
+        Ok(())
+    }
+
+    /// Runs the fixture at the given path in its own data directory, capturing the outcome
+    /// and any error in the returned [FixtureResult].
+    pub async fn run_fixture_file(&self, path: &Path) -> FixtureResult {
+        let mut result = FixtureResult::new(path);
+
+        // Decoding a fixture blocks on its whole witness data, so it is done off the async
+        // workers to let the other fixtures of a suite run concurrently.
+        let owned_path = path.to_path_buf();
+        let fixture = tokio::task::spawn_blocking(move || load_fixture(&owned_path))
+            .await
+            .map_err(|e| eyre!("Failed to load fixture: {}", e))
+            .and_then(|fixture| fixture);
+        let fixture = match fixture {
+            Ok(fixture) => fixture,
+            Err(e) => {
+                error!(target: TARGET, "Failed to load fixture {:?}: {}", path, e);
+                result.error = Some(e.to_string());
+                return result;
+            }
+        };
+        let expected_status = fixture.expected_status;
+        result.inputs = Some(InputsSummary::from(&fixture));
+        result.expected_status = Some(expected_status);
+
+        let stats = match self.workspace.create(TARGET) {
+            Ok(data_dir) => {
+                let stats = self
+                    .run_fixture(fixture, data_dir.path().to_path_buf())
+                    .await;
+                data_dir.finish(
+                    stats
+                        .as_ref()
+                        .is_ok_and(|stats| stats.status == expected_status),
+                );
+                stats
+            }
+            Err(e) => Err(e),
+        };
+        match stats {
+            Ok(stats) => {
+                if let Some(usage) = &stats.preimage_usage {
+                    if !usage.missing.is_empty() {
+                        error!(
+                            target: TARGET,
+                            "{:?} is missing {} requested preimages",
+                            path,
+                            usage.missing.len()
+                        );
+                    }
+                    info!(
+                        target: TARGET,
+                        "{:?}: {} preimages requested, {} witness entries unused",
+                        path,
+                        usage.requested,
+                        usage.unused.len()
+                    );
+                }
+                result.passed = stats.status == expected_status;
+                if result.passed {
+                    info!(target: TARGET, "PASS {:?}: status {:?}", path, stats.status);
+                } else {
+                    error!(
+                        target: TARGET,
+                        "FAIL {:?}: expected status {:?}, got {:?}",
+                        path,
+                        expected_status,
+                        stats.status
+                    );
+                }
+                result.stats = Some(stats);
+            }
+            Err(e) => {
+                error!(target: TARGET, "Failed to run fixture {:?}: {}", path, e);
+                result.error = Some(e.to_string());
+            }
+        }
+
+        result
+    }
+
+    /// Runs the given fixture through the configured program, using `data_dir` to store
+    /// the program inputs.
+    pub async fn run_fixture(
+        &self,
+        fixture: FaultProofFixture,
+        data_dir: PathBuf,
+    ) -> Result<ProgramStats> {
+        self.validate()?;
+        // The op-program host serves the local preimages of its own client, which reads the
+        // L2 chain config of custom chains where kona-client reads the rollup config.
+        if self.program == Program::Kona
+            && !self.preimage_server
+            && matches!(
+                fixture.inputs.chain_definition,
+                ChainDefinition::Unnamed(..)
+            )
+        {
+            return Err(eyre!(
+                "kona-client cannot be hosted by the op-program for an unnamed chain definition"
+            ));
+        }
+
+        let timeout = self.timeout.map(Duration::from_secs);
+        let mut op_program_command =
+            OpProgramCommand::new(self.op_program.clone(), fixture, data_dir, timeout);
+        if self.preimage_server {
+            op_program_command.preimage_server = Some(std::env::current_exe()?);
+            op_program_command.program = self.program;
+        }
+
+        let stats = match (self.cannon.as_ref(), self.asterisc.as_ref()) {
+            (Some(cannon), _) => {
+                let mut cannon_command = CannonCommand::new(
                     cannon.clone(),
                     self.cannon_state
                         .clone()
@@ -102,33 +464,122 @@ impl RunOpProgram {
                     self.cannon_meta
                         .clone()
                         .ok_or(eyre!("Missing cannon meta"))?,
+                    self.max_steps,
+                    timeout,
                     op_program_command,
                 );
-                cannon_command.prepare().await?;
-                let stats = cannon_command.run().await?;
-                info!(target: TARGET, "Cannon stats: {:?}", stats);
-
-                if let Some(output) = &self.output {
-                    let file = std::fs::File::create(output)?;
-                    serde_json::to_writer_pretty(file, &stats)?;
-                }
+                cannon_command.info_at = self.info_at.clone();
+                cannon_command.proof_at = self.proof_at.clone();
+                cannon_command.snapshot_at = self.snapshot_at.clone();
+                let stats = run_program(&cannon_command).await?;
+                self.track_preimages(stats, &cannon_command.op_program)?
             }
-            None => {
-                op_program_command.prepare().await?;
-                let stats = op_program_command.run().await?;
-                info!(target: TARGET, "op-program stats: {:?}", stats);
-
-                if let Some(output) = &self.output {
-                    let file = std::fs::File::create(output)?;
-                    serde_json::to_writer_pretty(file, &stats)?;
+            (None, Some(asterisc)) => {
+                let mut asterisc_command = AsteriscCommand::new(
+                    asterisc.clone(),
+                    self.asterisc_state
+                        .clone()
+                        .ok_or(eyre!("Missing asterisc state"))?,
+                    self.max_steps,
+                    timeout,
+                    op_program_command,
+                );
+                asterisc_command.info_at = self.info_at.clone();
+                let stats = run_program(&asterisc_command).await?;
+                self.track_preimages(stats, &asterisc_command.op_program)?
+            }
+            (None, None) => {
+                if self.program == Program::Kona {
+                    op_program_command.exec = Some(
+                        self.kona_client
+                            .clone()
+                            .ok_or(eyre!("Missing kona-client binary"))?,
+                    );
                 }
+                let stats = run_program(&op_program_command).await?;
+                self.track_preimages(stats, &op_program_command)?
             }
+        };
+        info!(target: TARGET, "{} stats: {:?}", self.name(), stats);
+
+        Ok(stats)
+    }
+
+    /// Adds the preimage usage of the completed run to its stats, if preimage tracking is
+    /// enabled.
+    fn track_preimages(
+        &self,
+        mut stats: ProgramStats,
+        op_program: &OpProgramCommand,
+    ) -> Result<ProgramStats> {
+        if self.track_preimages {
+            let requested = op_program.requested_preimages()?;
+            stats.preimage_usage = Some(PreimageUsage::new(&op_program.fixture, &requested));
         }
+        Ok(stats)
+    }
+}
 
-        std::fs::remove_dir_all(&data_dir)?;
+/// Prepares and runs the given fault proof program runner.
+async fn run_program<R: FaultProofRunner>(runner: &R) -> Result<ProgramStats> {
+    runner.prepare().await?;
+    runner.run().await
+}
 
-        Ok(())
+/// Parses a Cannon step pattern: `never`, `always`, `=<step>` to match a single step or
+/// `%<interval>` to match every multiple of the interval.
+fn parse_step_pattern(pattern: &str) -> Result<String, String> {
+    let valid = match pattern {
+        "never" | "always" => true,
+        _ => match pattern.split_at(pattern.len().min(1)) {
+            ("=", step) => step.parse::<u64>().is_ok(),
+            ("%", interval) => interval.parse::<u64>().is_ok_and(|interval| interval > 0),
+            _ => false,
+        },
+    };
+    if !valid {
+        return Err(format!(
+            "invalid step pattern {:?}, expected never, always, =<step> or %<interval>",
+            pattern
+        ));
+    }
+    Ok(pattern.to_string())
+}
+
+/// Returns the step of a Cannon checkpoint file with the given name, if it has the given
+/// prefix and suffix.
+fn checkpoint_step(file_name: &str, prefix: &str, suffix: &str) -> Option<u64> {
+    file_name
+        .strip_prefix(prefix)?
+        .strip_suffix(suffix)?
+        .parse()
+        .ok()
+}
+
+/// Returns the steps of the Cannon checkpoint files in the given directory, if it exists.
+fn checkpoint_steps(dir: &Path, prefix: &str, suffix: &str) -> Result<Vec<u64>> {
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+    let mut steps = Vec::new();
+    for entry in std::fs::read_dir(dir)? {
+        let name = entry?.file_name();
+        if let Some(step) = name
+            .to_str()
+            .and_then(|name| checkpoint_step(name, prefix, suffix))
+        {
+            steps.push(step);
+        }
     }
+    Ok(steps)
+}
+
+/// Reads and decodes the fault proof fixture at the given path, in any supported encoding.
+pub fn load_fixture(path: &Path) -> Result<FaultProofFixture> {
+    let fixture = std::fs::read(path).map_err(|e| eyre!("Failed to read fixture file: {}", e))?;
+    let (fixture, _) = FaultProofFixture::decode(&fixture)
+        .map_err(|e| eyre!("Failed to parse fixture file: {}", e))?;
+    Ok(fixture)
 }
 
 /// The command to run the op-program within cannon.
@@ -144,6 +595,18 @@ pub struct CannonCommand {
     pub output: PathBuf,
     /// The path to the cannon debug output file.
     pub debug: PathBuf,
+    /// The path to the file capturing the output of cannon.
+    pub log: PathBuf,
+    /// The maximum number of steps to run cannon for.
+    pub max_steps: Option<u64>,
+    /// The steps at which cannon logs its progress.
+    pub info_at: String,
+    /// The steps at which cannon writes a proof, if any.
+    pub proof_at: Option<String>,
+    /// The steps at which cannon writes a state snapshot, if any.
+    pub snapshot_at: Option<String>,
+    /// The wall clock timeout for the cannon run.
+    pub timeout: Option<Duration>,
     /// The op-program command to run within cannon.
     pub op_program: OpProgramCommand,
 }
@@ -153,10 +616,13 @@ impl CannonCommand {
         cannon: PathBuf,
         state: PathBuf,
         meta: PathBuf,
+        max_steps: Option<u64>,
+        timeout: Option<Duration>,
         op_program: OpProgramCommand,
     ) -> Self {
         let output = op_program.data_dir.join("cannon-output.bin");
         let debug = op_program.data_dir.join("cannon-debug.json");
+        let log = op_program.data_dir.join("cannon.log");
 
         Self {
             cannon,
@@ -164,34 +630,192 @@ impl CannonCommand {
             meta,
             output,
             debug,
+            log,
+            max_steps,
+            info_at: "%10000000".to_string(),
+            proof_at: None,
+            snapshot_at: None,
+            timeout,
             op_program,
         }
     }
 
-    pub async fn prepare(&self) -> Result<()> {
+    /// Returns the directory cannon writes its proofs to.
+    pub fn proof_dir(&self) -> PathBuf {
+        self.op_program.data_dir.join("proofs")
+    }
+
+    /// Returns the directory cannon writes its state snapshots to.
+    pub fn snapshot_dir(&self) -> PathBuf {
+        self.op_program.data_dir.join("snapshots")
+    }
+
+    pub fn args(&self) -> Vec<String> {
+        let mut args = vec![
+            "run".to_string(),
+            "--info-at".to_string(),
+            self.info_at.clone(),
+            "--input".to_string(),
+            self.state.to_str().unwrap().to_string(),
+            "--meta".to_string(),
+            self.meta.to_str().unwrap().to_string(),
+            "--output".to_string(),
+            self.output.to_str().unwrap().to_string(),
+            "--debug-info".to_string(),
+            self.debug.to_str().unwrap().to_string(),
+        ];
+        if let Some(max_steps) = self.max_steps {
+            args.push("--stop-at".to_string());
+            args.push(format!("={}", max_steps));
+        }
+        if let Some(proof_at) = &self.proof_at {
+            let proof_fmt = self
+                .proof_dir()
+                .join(format!("{}%d{}", PROOF_PREFIX, PROOF_SUFFIX));
+            args.push("--proof-at".to_string());
+            args.push(proof_at.clone());
+            args.push("--proof-fmt".to_string());
+            args.push(proof_fmt.to_str().unwrap().to_string());
+        }
+        if let Some(snapshot_at) = &self.snapshot_at {
+            let snapshot_fmt = self
+                .snapshot_dir()
+                .join(format!("{}%d{}", SNAPSHOT_PREFIX, SNAPSHOT_SUFFIX));
+            args.push("--snapshot-at".to_string());
+            args.push(snapshot_at.clone());
+            args.push("--snapshot-fmt".to_string());
+            args.push(snapshot_fmt.to_str().unwrap().to_string());
+        }
+        args.extend(self.op_program.server_args());
+        args
+    }
+
+    /// Parses the progress logged by cannon at each `--info-at` step from its captured output.
+    fn progress(&self) -> Option<Vec<ProgressSample>> {
+        let output = match std::fs::read(&self.log) {
+            Ok(output) => output,
+            Err(e) => {
+                warn!(target: TARGET, "Failed to read cannon log {:?}: {}", self.log, e);
+                return None;
+            }
+        };
+        let progress = parse_progress(&String::from_utf8_lossy(&output));
+        for sample in &progress {
+            debug!(
+                target: TARGET,
+                "Cannon at step {}: {} ips in {}",
+                sample.step,
+                sample.ips.unwrap_or_default(),
+                sample.name.as_deref().unwrap_or("unknown")
+            );
+        }
+        Some(progress)
+    }
+
+    /// Collects the checkpoints of the run from the proofs and snapshots written by cannon,
+    /// ordered by step. Returns [None] if neither were requested.
+    fn checkpoints(&self) -> Result<Option<Vec<Checkpoint>>> {
+        if self.proof_at.is_none() && self.snapshot_at.is_none() {
+            return Ok(None);
+        }
+
+        let mut checkpoints = BTreeMap::new();
+        for step in checkpoint_steps(&self.proof_dir(), PROOF_PREFIX, PROOF_SUFFIX)? {
+            checkpoints
+                .entry(step)
+                .or_insert_with(|| Checkpoint::new(step))
+                .proof = true;
+        }
+        let snapshot_dir = self.snapshot_dir();
+        for step in checkpoint_steps(&snapshot_dir, SNAPSHOT_PREFIX, SNAPSHOT_SUFFIX)? {
+            let path = snapshot_dir.join(format!("{}{}{}", SNAPSHOT_PREFIX, step, SNAPSHOT_SUFFIX));
+            match VersionedState::read_file(&path) {
+                Ok(state) => {
+                    let pages = state.memory().pages.len() as u64;
+                    let checkpoint = checkpoints
+                        .entry(step)
+                        .or_insert_with(|| Checkpoint::new(step));
+                    checkpoint.pages = Some(pages);
+                    checkpoint.memory_used = Some(pages * PAGE_SIZE);
+                }
+                Err(e) => warn!(target: TARGET, "Failed to read snapshot {:?}: {}", path, e),
+            }
+        }
+
+        let checkpoints = checkpoints.into_values().collect::<Vec<_>>();
+        for checkpoint in &checkpoints {
+            debug!(
+                target: TARGET,
+                "Cannon checkpoint at step {}: {} pages, proof: {}",
+                checkpoint.step,
+                checkpoint.pages.map_or("unknown".to_string(), |p| p.to_string()),
+                checkpoint.proof
+            );
+        }
+        Ok(Some(checkpoints))
+    }
+}
+
+impl FaultProofRunner for CannonCommand {
+    async fn prepare(&self) -> Result<()> {
         self.op_program.prepare().await?;
 
+        if self.proof_at.is_some() {
+            std::fs::create_dir_all(self.proof_dir())?;
+        }
+        if self.snapshot_at.is_some() {
+            std::fs::create_dir_all(self.snapshot_dir())?;
+        }
+
         Ok(())
     }
 
-    pub async fn run(&self) -> Result<ProgramStats> {
+    async fn run(&self) -> Result<ProgramStats> {
         let start = std::time::Instant::now();
 
-        let result = Command::new(&self.cannon).args(self.args()).status();
-
-        if result.is_err() {
-            return Err(eyre!("Failed to execute cannon binary"));
-        }
+        // The output is captured to parse the progress cannon logs at each `--info-at` step.
+        let log = std::fs::File::create(&self.log)?;
+        let mut command = Command::new(&self.cannon);
+        command
+            .args(self.args())
+            .stdout(Stdio::from(log.try_clone()?))
+            .stderr(Stdio::from(log));
+        let result = run_with_timeout(&mut command, self.timeout)
+            .await
+            .map_err(|e| eyre!("Failed to execute cannon binary: {}", e))?;
 
         let runtime = start.elapsed().as_millis();
+        let progress = self.progress();
 
-        let data =
-            std::fs::read(&self.output).map_err(|e| eyre!("Failed to read output file: {}", e))?;
+        let Some(result) = result else {
+            warn!(target: TARGET, "Cannon timed out after {}ms", runtime);
+            if let Some(last) = progress.as_ref().and_then(|progress| progress.last()) {
+                warn!(
+                    target: TARGET,
+                    "Cannon was last at step {} in {}",
+                    last.step,
+                    last.name.as_deref().unwrap_or("unknown")
+                );
+            }
+            return Ok(ProgramStats {
+                status: FaultProofStatus::Unfinished,
+                runtime,
+                progress,
+                ..ProgramStats::default()
+            });
+        };
+        if !result.success() {
+            return Err(eyre!(
+                "Cannon exited with {}, see its output in {:?}",
+                result,
+                self.log
+            ));
+        }
 
-        let versioned_state = VersionedState::try_from(data)
-            .map_err(|e| eyre!("Failed to decode versioned state: {}", e))?;
+        let versioned_state = VersionedState::read_file(&self.output)
+            .map_err(|e| eyre!("Failed to read versioned state: {}", e))?;
         let output: CannonOutput = CannonOutput {
-            step: versioned_state.single_threaded_fpvmstate.step,
+            step: versioned_state.step(),
         };
 
         let debug_output = std::fs::read_to_string(&self.debug)
@@ -199,41 +823,138 @@ impl CannonCommand {
         let debug_output: CannonDebug = serde_json::from_str(&debug_output)?;
 
         let stats = ProgramStats {
+            status: versioned_state.status(),
             runtime,
             instructions: Some(output.step),
             pages: Some(debug_output.pages),
             memory_used: Some(debug_output.memory_used.to()),
             num_preimage_requests: Some(debug_output.num_preimage_requests),
             total_preimage_size: Some(debug_output.total_preimage_size),
+            checkpoints: self.checkpoints()?,
+            progress,
+            ..ProgramStats::default()
         };
 
         Ok(stats)
     }
+}
+
+/// The command to run a fault proof program within asterisc.
+#[derive(Debug)]
+pub struct AsteriscCommand {
+    /// The path to the asterisc binary.
+    pub asterisc: PathBuf,
+    /// The path to the asterisc state file.
+    pub state: PathBuf,
+    /// The path to the asterisc output file.
+    pub output: PathBuf,
+    /// The maximum number of steps to run asterisc for.
+    pub max_steps: Option<u64>,
+    /// The steps at which asterisc logs its progress.
+    pub info_at: String,
+    /// The wall clock timeout for the asterisc run.
+    pub timeout: Option<Duration>,
+    /// The op-program command serving the preimages to the program within asterisc.
+    pub op_program: OpProgramCommand,
+}
+
+/// The subset of the asterisc VM state used to report the program outcome.
+#[derive(Debug, Deserialize)]
+struct AsteriscOutput {
+    pub step: u64,
+    pub exited: bool,
+    pub exit: u8,
+}
+
+impl AsteriscCommand {
+    pub fn new(
+        asterisc: PathBuf,
+        state: PathBuf,
+        max_steps: Option<u64>,
+        timeout: Option<Duration>,
+        op_program: OpProgramCommand,
+    ) -> Self {
+        let output = op_program.data_dir.join("asterisc-output.json");
+
+        Self {
+            asterisc,
+            state,
+            output,
+            max_steps,
+            info_at: "%10000000".to_string(),
+            timeout,
+            op_program,
+        }
+    }
 
     pub fn args(&self) -> Vec<String> {
         let mut args = vec![
             "run".to_string(),
             "--info-at".to_string(),
-            "%10000000".to_string(),
+            self.info_at.clone(),
             "--input".to_string(),
             self.state.to_str().unwrap().to_string(),
-            "--meta".to_string(),
-            self.meta.to_str().unwrap().to_string(),
             "--output".to_string(),
             self.output.to_str().unwrap().to_string(),
-            "--debug-info".to_string(),
-            self.debug.to_str().unwrap().to_string(),
-            "--".to_string(),
-            self.op_program.op_program.to_str().unwrap().to_string(),
         ];
-        args.extend(self.op_program.args());
-        args.push("--server".to_string());
+        if let Some(max_steps) = self.max_steps {
+            args.push("--stop-at".to_string());
+            args.push(format!("={}", max_steps));
+        }
+        args.extend(self.op_program.server_args());
         args
     }
 }
 
+impl FaultProofRunner for AsteriscCommand {
+    async fn prepare(&self) -> Result<()> {
+        self.op_program.prepare().await
+    }
+
+    async fn run(&self) -> Result<ProgramStats> {
+        let start = std::time::Instant::now();
+
+        let mut command = Command::new(&self.asterisc);
+        command.args(self.args());
+        let result = run_with_timeout(&mut command, self.timeout)
+            .await
+            .map_err(|e| eyre!("Failed to execute asterisc binary: {}", e))?;
+
+        let runtime = start.elapsed().as_millis();
+
+        let Some(result) = result else {
+            warn!(target: TARGET, "Asterisc timed out after {}ms", runtime);
+            return Ok(ProgramStats {
+                status: FaultProofStatus::Unfinished,
+                runtime,
+                ..ProgramStats::default()
+            });
+        };
+        if !result.success() {
+            return Err(eyre!("Asterisc exited with {}", result));
+        }
+
+        let output = std::fs::read_to_string(&self.output)
+            .map_err(|e| eyre!("Failed to read output file: {}", e))?;
+        let output: AsteriscOutput = serde_json::from_str(&output)?;
+
+        let status = if output.exited {
+            status_from_exit_code(output.exit)
+        } else {
+            FaultProofStatus::Unfinished
+        };
+
+        Ok(ProgramStats {
+            status,
+            runtime,
+            instructions: Some(output.step),
+            ..ProgramStats::default()
+        })
+    }
+}
+
 /// The command to run the op-program.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct OpProgramCommand {
     /// The path to the op-program binary.
     pub op_program: PathBuf,
@@ -241,62 +962,132 @@ pub struct OpProgramCommand {
     pub fixture: FaultProofFixture,
     /// The directory to store the input data for the op-program.
     pub data_dir: PathBuf,
+    /// The wall clock timeout for the op-program run.
+    pub timeout: Option<Duration>,
+    /// The optional client binary run by the op-program host in place of its own client.
+    pub exec: Option<PathBuf>,
+    /// The optional opfp binary serving the preimages in place of the op-program host, when
+    /// running within a VM.
+    pub preimage_server: Option<PathBuf>,
+    /// The program run within the VM, whose local preimage layout the preimage server uses.
+    pub program: Program,
 }
 
 impl OpProgramCommand {
-    pub fn new(op_program: PathBuf, fixture: FaultProofFixture, data_dir: PathBuf) -> Self {
+    pub fn new(
+        op_program: PathBuf,
+        fixture: FaultProofFixture,
+        data_dir: PathBuf,
+        timeout: Option<Duration>,
+    ) -> Self {
         Self {
             op_program,
             fixture,
             data_dir,
+            timeout,
+            exec: None,
+            preimage_server: None,
+            program: Program::OpProgram,
         }
     }
 
-    pub async fn prepare(&self) -> Result<()> {
-        if let ChainDefinition::Unnamed(rollup_config, genesis) =
-            &self.fixture.inputs.chain_definition
-        {
-            // Write the genesis file to the temp directory.
-            let genesis_file = self.data_dir.join("genesis.json");
-            let file = std::fs::File::create(&genesis_file)?;
-            serde_json::to_writer_pretty(file, &genesis)?;
-
-            // Write the rollup config to the temp directory.
-            let rollup_config_file = self.data_dir.join("rollup_config.json");
-            let file = std::fs::File::create(&rollup_config_file)?;
-            let cfg: RollupConfig = rollup_config.into();
-            serde_json::to_writer_pretty(file, &cfg)?;
+    /// Returns the arguments to run the op-program host as the preimage server of a program
+    /// running within a VM, prefixed by the `--` separating them from the VM arguments.
+    pub fn server_args(&self) -> Vec<String> {
+        if let Some(opfp) = &self.preimage_server {
+            let program = self
+                .program
+                .to_possible_value()
+                .expect("programs have a name");
+            return vec![
+                "--".to_string(),
+                opfp.to_str().unwrap().to_string(),
+                "serve-preimages".to_string(),
+                "--fixture".to_string(),
+                self.fixture_file().to_str().unwrap().to_string(),
+                "--program".to_string(),
+                program.get_name().to_string(),
+                "--requests".to_string(),
+                self.requests_file().to_str().unwrap().to_string(),
+            ];
         }
 
-        for (key, value) in &self.fixture.witness_data {
-            let key_hex = key.encode_hex();
-
-            let (dirname, filename) = key_hex.split_at(4);
-            let dirname = self.data_dir.join(dirname);
-            std::fs::create_dir_all(&dirname)?;
+        let mut args = vec![
+            "--".to_string(),
+            self.op_program.to_str().unwrap().to_string(),
+        ];
+        args.extend(self.args());
+        args.push("--server".to_string());
+        args
+    }
 
-            let file = dirname.join(format!("{}.txt", filename));
-            std::fs::write(file, value.encode_hex())?;
-        }
+    /// Returns the path of the fixture file read by the preimage server.
+    pub fn fixture_file(&self) -> PathBuf {
+        self.data_dir.join("fixture.bin")
+    }
 
-        Ok(())
+    /// Returns the path of the file the preimage server writes the requested keys to, one per
+    /// line.
+    pub fn requests_file(&self) -> PathBuf {
+        self.data_dir.join("requested-preimages.json")
     }
 
-    pub async fn run(&self) -> Result<ProgramStats> {
-        let start = std::time::Instant::now();
+    /// Returns the path of the file capturing the output of a native op-program run.
+    pub fn log_file(&self) -> PathBuf {
+        self.data_dir.join("op-program.log")
+    }
 
-        let result = Command::new(&self.op_program).args(self.args()).status();
+    /// Returns the path of the file the op-program host reads the preimage of the given key
+    /// from.
+    pub fn witness_file(&self, key: &B256) -> PathBuf {
+        let key_hex = key.encode_hex();
+        let (dirname, filename) = key_hex.split_at(4);
+        self.data_dir
+            .join(dirname)
+            .join(format!("{}.txt", filename))
+    }
 
-        if result.is_err() {
-            return Err(eyre!("Failed to execute op-program binary"));
+    /// Returns the keys of the preimages requested by the completed run.
+    ///
+    /// The preimage server records every requested key. Otherwise the witness files read by
+    /// the op-program host are found from their access times, which are reset to the epoch
+    /// when the files are written and updated once they are read. This does not record the
+    /// local or missing keys, and fails if reading a file does not update its access time,
+    /// e.g. on a data directory mounted with `noatime`.
+    pub fn requested_preimages(&self) -> Result<BTreeSet<B256>> {
+        if self.preimage_server.is_some() {
+            let requests = std::fs::read_to_string(self.requests_file())
+                .map_err(|e| eyre!("Failed to read requested preimages: {}", e))?;
+            return requests
+                .lines()
+                .map(|key| {
+                    key.parse()
+                        .map_err(|e| eyre!("Invalid requested preimage key {:?}: {}", key, e))
+                })
+                .collect();
         }
 
-        let runtime = start.elapsed().as_millis();
+        let probe = self.data_dir.join(ATIME_PROBE_FILE);
+        write_unaccessed(&probe, b"probe")?;
+        std::fs::read(&probe)?;
+        let probe_accessed = std::fs::metadata(&probe)?.accessed()?;
+        std::fs::remove_file(&probe)?;
+        if probe_accessed <= UNIX_EPOCH {
+            return Err(eyre!(
+                "Reading files in {:?} does not update their access times, so the preimages \
+                 read by the op-program cannot be tracked. Use a data directory mounted without \
+                 noatime, or the preimage server within a VM",
+                self.data_dir
+            ));
+        }
 
-        Ok(ProgramStats {
-            runtime,
-            ..ProgramStats::default()
-        })
+        let mut requested = BTreeSet::new();
+        for key in self.fixture.witness_data.keys() {
+            if std::fs::metadata(self.witness_file(key))?.accessed()? > UNIX_EPOCH {
+                requested.insert(*key);
+            }
+        }
+        Ok(requested)
     }
 
     pub fn args(&self) -> Vec<String> {
@@ -338,6 +1129,346 @@ impl OpProgramCommand {
                 );
             }
         }
+        if let Some(exec) = &self.exec {
+            args.push("--exec".to_string());
+            args.push(exec.to_str().unwrap().to_string());
+        }
         args
     }
 }
+
+impl OpProgramCommand {
+    /// Writes the chain definition and witness data of the fixture for the op-program host,
+    /// or the fixture itself for the preimage server.
+    fn write_inputs(&self) -> Result<()> {
+        // The preimage server reads the witness data from a single fixture file rather than
+        // a file per preimage.
+        if self.preimage_server.is_some() {
+            return self.fixture.save(&self.fixture_file());
+        }
+
+        if let ChainDefinition::Unnamed(rollup_config, genesis) =
+            &self.fixture.inputs.chain_definition
+        {
+            // Write the genesis file to the temp directory.
+            let genesis_file = self.data_dir.join("genesis.json");
+            let file = std::fs::File::create(&genesis_file)?;
+            serde_json::to_writer_pretty(file, &genesis)?;
+
+            // Write the rollup config to the temp directory.
+            let rollup_config_file = self.data_dir.join("rollup_config.json");
+            let file = std::fs::File::create(&rollup_config_file)?;
+            let cfg: RollupConfig = rollup_config.into();
+            serde_json::to_writer_pretty(file, &cfg)?;
+        }
+
+        for (key, value) in &self.fixture.witness_data {
+            let file = self.witness_file(key);
+            if let Some(dirname) = file.parent() {
+                std::fs::create_dir_all(dirname)?;
+            }
+            write_unaccessed(&file, value.encode_hex().as_bytes())?;
+        }
+
+        Ok(())
+    }
+}
+
+impl FaultProofRunner for OpProgramCommand {
+    async fn prepare(&self) -> Result<()> {
+        // The witness data is written off the async workers, as it blocks on a file per
+        // preimage.
+        let command = self.clone();
+        tokio::task::spawn_blocking(move || command.write_inputs()).await?
+    }
+
+    async fn run(&self) -> Result<ProgramStats> {
+        let start = std::time::Instant::now();
+
+        // The output is captured to tell an invalid claim from a host error, as the
+        // op-program host exits with 1 on both.
+        let log_file = self.log_file();
+        let log = std::fs::File::create(&log_file)?;
+        let mut command = Command::new(&self.op_program);
+        command
+            .args(self.args())
+            .stdout(Stdio::from(log.try_clone()?))
+            .stderr(Stdio::from(log));
+        let result = run_with_timeout(&mut command, self.timeout)
+            .await
+            .map_err(|e| eyre!("Failed to execute op-program binary: {}", e))?;
+
+        let runtime = start.elapsed().as_millis();
+
+        let status = match result {
+            Some(result) => {
+                let output = std::fs::read(&log_file)?;
+                native_status(result, &String::from_utf8_lossy(&output)).map_err(|e| {
+                    eyre!(
+                        "op-program exited with {}: {}, see its output in {:?}",
+                        result,
+                        e,
+                        log_file
+                    )
+                })?
+            }
+            None => {
+                warn!(target: TARGET, "op-program timed out after {}ms", runtime);
+                FaultProofStatus::Unfinished
+            }
+        };
+
+        Ok(ProgramStats {
+            status,
+            runtime,
+            ..ProgramStats::default()
+        })
+    }
+}
+
+/// Writes the file with its access and modification times reset to the epoch, so that any
+/// later read of the file updates its access time, even with `relatime`.
+fn write_unaccessed(path: &Path, contents: &[u8]) -> Result<()> {
+    let mut file = std::fs::File::create(path)?;
+    std::io::Write::write_all(&mut file, contents)?;
+    file.set_times(
+        FileTimes::new()
+            .set_accessed(UNIX_EPOCH)
+            .set_modified(UNIX_EPOCH),
+    )?;
+    Ok(())
+}
+
+/// Runs the given command to completion, killing it if it is still running after the
+/// timeout. Returns [None] if the process timed out.
+///
+/// The command runs in its own process group, which is killed on timeout or if the run is
+/// dropped, so that the processes it spawned, such as the preimage host and the client of a
+/// VM, are not left running.
+async fn run_with_timeout(
+    command: &mut Command,
+    timeout: Option<Duration>,
+) -> Result<Option<ExitStatus>> {
+    let mut child = command.process_group(0).kill_on_drop(true).spawn()?;
+    let mut group = ProcessGroup(child.id());
+    let status = match timeout {
+        None => Some(child.wait().await?),
+        Some(timeout) => match tokio::time::timeout(timeout, child.wait()).await {
+            Ok(status) => Some(status?),
+            Err(_) => None,
+        },
+    };
+
+    if status.is_some() {
+        // The child has been waited on, so its id may be reused by another process group.
+        group.0 = None;
+    } else {
+        group.kill();
+        child.wait().await?;
+    }
+    Ok(status)
+}
+
+/// The process group led by a spawned child process, killed when dropped unless the child
+/// has exited.
+#[derive(Debug)]
+struct ProcessGroup(Option<u32>);
+
+impl ProcessGroup {
+    /// Kills every process in the group.
+    fn kill(&mut self) {
+        if let Some(id) = self.0.take() {
+            // SAFETY: killpg has no memory safety requirements, and the group is led by a
+            // child which has not been waited on, so its id has not been reused.
+            unsafe { libc::killpg(id as libc::pid_t, libc::SIGKILL) };
+        }
+    }
+}
+
+impl Drop for ProcessGroup {
+    fn drop(&mut self) {
+        self.kill();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy_primitives::Bytes;
+    use fp_test_fixtures::PreimageKey;
+
+    #[test]
+    fn test_preimage_usage() {
+        let mut fixture = FaultProofFixture::default();
+        let used = B256::from([2; 32]);
+        let unused = B256::from([4; 32]);
+        fixture.witness_data.insert(used, Bytes::new());
+        fixture.witness_data.insert(unused, Bytes::new());
+
+        let missing = B256::from([5; 32]);
+        let local = B256::from(PreimageKey::new_local(1));
+        let requested = BTreeSet::from([used, missing, local]);
+
+        assert_eq!(
+            PreimageUsage::new(&fixture, &requested),
+            PreimageUsage {
+                requested: 3,
+                missing: BTreeSet::from([missing]),
+                unused: BTreeSet::from([unused]),
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_requested_preimages_from_access_times() {
+        let mut fixture = FaultProofFixture::default();
+        let read = B256::from([2; 32]);
+        let unread = B256::from([3; 32]);
+        fixture.witness_data.insert(read, Bytes::from_static(&[1]));
+        fixture
+            .witness_data
+            .insert(unread, Bytes::from_static(&[2]));
+
+        let data_dir = std::env::temp_dir().join(format!("opfp-atime-test-{}", std::process::id()));
+        std::fs::create_dir_all(&data_dir).unwrap();
+        let command = OpProgramCommand::new(PathBuf::new(), fixture, data_dir.clone(), None);
+        command.prepare().await.unwrap();
+        std::fs::read(command.witness_file(&read)).unwrap();
+
+        // Either the read is observed, or tracking fails rather than missing it.
+        match command.requested_preimages() {
+            Ok(requested) => assert_eq!(requested, BTreeSet::from([read])),
+            Err(e) => assert!(e.to_string().contains("access times"), "{}", e),
+        }
+        std::fs::remove_dir_all(data_dir).unwrap();
+    }
+
+    #[test]
+    fn test_validate_program_args() {
+        let args = |flags: &[&str]| {
+            let mut argv = vec!["opfp", "--op-program", "op-program"];
+            argv.extend(flags);
+            ProgramArgs::try_parse_from(argv).expect("failed to parse")
+        };
+
+        let valid = [
+            args(&[]),
+            args(&[
+                "--cannon",
+                "cannon",
+                "--proof-at",
+                "=1",
+                "--preimage-server",
+            ]),
+            args(&["--program", "kona", "--kona-client", "kona-client"]),
+            args(&[
+                "--program",
+                "kona",
+                "--asterisc",
+                "asterisc",
+                "--info-at",
+                "never",
+            ]),
+        ];
+        for program in &valid {
+            program.validate().expect("failed to validate");
+        }
+        assert_eq!(valid[3].name(), "kona-asterisc");
+
+        for invalid in [
+            args(&["--asterisc", "asterisc"]),
+            args(&["--program", "kona"]),
+            args(&["--preimage-server"]),
+            args(&[
+                "--asterisc",
+                "asterisc",
+                "--program",
+                "kona",
+                "--snapshot-at",
+                "%10",
+            ]),
+        ] {
+            assert!(invalid.validate().is_err(), "{:?}", invalid);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_kona_unnamed_chain() {
+        let program = ProgramArgs::try_parse_from([
+            "opfp",
+            "--op-program",
+            "op-program",
+            "--program",
+            "kona",
+            "--kona-client",
+            "kona-client",
+        ])
+        .expect("failed to parse");
+        let mut fixture = FaultProofFixture::default();
+        fixture.inputs.chain_definition =
+            ChainDefinition::Unnamed(Default::default(), Default::default());
+
+        let error = program
+            .run_fixture(fixture, PathBuf::from("unused"))
+            .await
+            .expect_err("kona ran with an unnamed chain");
+        assert!(error.to_string().contains("unnamed chain"), "{}", error);
+    }
+
+    #[tokio::test]
+    async fn test_run_with_timeout_kills_process_group() {
+        let pid_file =
+            std::env::temp_dir().join(format!("opfp-timeout-test-{}", std::process::id()));
+        let mut command = Command::new("sh");
+        command.arg("-c").arg(format!(
+            "sleep 30 & echo $! > {}; wait",
+            pid_file.to_str().unwrap()
+        ));
+        let start = std::time::Instant::now();
+        let status = run_with_timeout(&mut command, Some(Duration::from_millis(500)))
+            .await
+            .expect("failed to run");
+        assert!(status.is_none());
+        assert!(start.elapsed() < Duration::from_secs(10));
+
+        // The orphaned sleep is killed along with the shell, at most leaving a zombie.
+        let pid = std::fs::read_to_string(&pid_file).unwrap();
+        std::fs::remove_file(&pid_file).unwrap();
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        let stat = std::fs::read_to_string(format!("/proc/{}/stat", pid.trim()));
+        assert!(
+            stat.as_deref().map_or(true, |stat| stat.contains(") Z ")),
+            "{:?}",
+            stat
+        );
+
+        let mut command = Command::new("true");
+        let status = run_with_timeout(&mut command, Some(Duration::from_secs(10)))
+            .await
+            .expect("failed to run");
+        assert!(status.is_some_and(|status| status.success()));
+    }
+
+    #[test]
+    fn test_checkpoint_patterns() {
+        for pattern in ["never", "always", "=0", "=1000", "%10000000"] {
+            assert_eq!(parse_step_pattern(pattern), Ok(pattern.to_string()));
+        }
+        for pattern in ["", "=", "%0", "%-1", "1000", "sometimes"] {
+            assert!(parse_step_pattern(pattern).is_err());
+        }
+
+        assert_eq!(
+            checkpoint_step("state-1000.bin.gz", SNAPSHOT_PREFIX, SNAPSHOT_SUFFIX),
+            Some(1000)
+        );
+        assert_eq!(
+            checkpoint_step("proof-42.json", PROOF_PREFIX, PROOF_SUFFIX),
+            Some(42)
+        );
+        assert_eq!(
+            checkpoint_step("state-final.bin.gz", SNAPSHOT_PREFIX, SNAPSHOT_SUFFIX),
+            None
+        );
+    }
+}
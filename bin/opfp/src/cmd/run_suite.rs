@@ -0,0 +1,170 @@
+//! Run Suite Subcommand
+
+use clap::{ArgAction, Parser};
+use color_eyre::{eyre::eyre, Result};
+use fp_test_fixtures::{FaultProofStatus, FixtureEncoding};
+use futures::{stream, StreamExt};
+use std::path::{Path, PathBuf};
+use tracing::info;
+
+use super::report::{FixtureResult, Report, ReportArgs};
+use super::run_op_program::ProgramArgs;
+
+/// The logging target to use for [tracing].
+const TARGET: &str = "run-suite";
+
+/// CLI arguments for the `run-suite` subcommand of `opfp`.
+#[derive(Parser, Clone, Debug)]
+pub struct RunSuite {
+    /// The fault proof program to run the fixtures with
+    #[command(flatten)]
+    pub program: ProgramArgs,
+    /// Directory or glob pattern of the fixture files to run
+    #[clap(
+        short,
+        long,
+        help = "Directory or glob pattern of the fixture files to run"
+    )]
+    pub fixtures: String,
+    /// The number of fixtures to run concurrently
+    #[clap(
+        short,
+        long,
+        default_value_t = 1,
+        value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..),
+        help = "Number of fixtures to run concurrently"
+    )]
+    pub jobs: usize,
+    /// The structured reports to write
+    #[command(flatten)]
+    pub report: ReportArgs,
+    /// Verbosity level (0-4)
+    #[arg(long, short, help = "Verbosity level (0-4)", action = ArgAction::Count)]
+    pub v: u8,
+}
+
+impl RunSuite {
+    /// Runs the `run-suite` subcommand.
+    pub async fn run(&self) -> Result<()> {
+        let fixtures = discover_fixtures(&self.fixtures)?;
+        if fixtures.is_empty() {
+            return Err(eyre!("No fixtures found matching {}", self.fixtures));
+        }
+        info!(
+            target: TARGET,
+            "Running {} fixtures with {} jobs",
+            fixtures.len(),
+            self.jobs
+        );
+
+        let results: Vec<FixtureResult> = stream::iter(&fixtures)
+            .map(|fixture| self.program.run_fixture_file(fixture))
+            .buffered(self.jobs)
+            .collect()
+            .await;
+
+        let report = Report::new(results);
+        print_summary(&report);
+        self.report.write(&report, self.program.name())?;
+
+        if report.failed > 0 {
+            return Err(eyre!(
+                "{} of {} fixtures failed",
+                report.failed,
+                fixtures.len()
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+/// Returns the fixture files at the given directory, searched recursively, or matching the
+/// given glob pattern.
+pub fn discover_fixtures(pattern: &str) -> Result<Vec<PathBuf>> {
+    let mut fixtures = Vec::new();
+
+    let path = Path::new(pattern);
+    if path.is_dir() {
+        collect_fixtures(path, &mut fixtures)?;
+    } else {
+        for entry in glob::glob(pattern)? {
+            let entry = entry?;
+            if entry.is_file() {
+                fixtures.push(entry);
+            }
+        }
+    }
+
+    fixtures.sort();
+    Ok(fixtures)
+}
+
+/// Recursively collects the fixture files within the given directory.
+fn collect_fixtures(dir: &Path, fixtures: &mut Vec<PathBuf>) -> Result<()> {
+    for entry in dir.read_dir()? {
+        let path = entry?.path();
+        if path.is_dir() {
+            collect_fixtures(&path, fixtures)?;
+        } else if FixtureEncoding::is_fixture_path(&path) {
+            fixtures.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// Prints a human-readable summary of the suite results to stdout.
+fn print_summary(summary: &Report) {
+    for result in &summary.results {
+        let verdict = if result.passed { "PASS" } else { "FAIL" };
+        match (&result.stats, &result.error) {
+            (Some(stats), _) => println!(
+                "{} {} (expected {:?}, got {:?}, {}ms)",
+                verdict,
+                result.fixture.display(),
+                fixture_status(result.expected_status),
+                stats.status,
+                stats.runtime
+            ),
+            (None, Some(error)) => {
+                println!("{} {} ({})", verdict, result.fixture.display(), error)
+            }
+            (None, None) => println!("{} {}", verdict, result.fixture.display()),
+        }
+    }
+    println!(
+        "{} passed, {} failed, {} total",
+        summary.passed,
+        summary.failed,
+        summary.results.len()
+    );
+}
+
+/// Returns the expected status of a fixture, or [FaultProofStatus::Unknown] if it could not
+/// be loaded.
+fn fixture_status(status: Option<FaultProofStatus>) -> FaultProofStatus {
+    status.unwrap_or(FaultProofStatus::Unknown)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_jobs() {
+        let parse = |jobs: &str| {
+            RunSuite::try_parse_from([
+                "run-suite",
+                "--op-program",
+                "op-program",
+                "--fixtures",
+                "fixtures",
+                "--jobs",
+                jobs,
+            ])
+        };
+        assert_eq!(parse("4").expect("failed to parse").jobs, 4);
+        let error = parse("0").expect_err("parsed zero jobs");
+        assert_eq!(error.kind(), clap::error::ErrorKind::ValueValidation);
+    }
+}
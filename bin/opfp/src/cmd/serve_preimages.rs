@@ -0,0 +1,359 @@
+//! Serve Preimages Subcommand
+
+use alloy_primitives::{Bytes, B256};
+use clap::{ArgAction, Parser};
+use color_eyre::{eyre::eyre, Result};
+use fp_test_fixtures::{ChainDefinition, FaultProofFixture, PreimageKey};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::File;
+use std::io::{ErrorKind, Read, Write};
+use std::os::unix::io::FromRawFd;
+use std::path::PathBuf;
+use tracing::{debug, error, info, trace};
+
+use super::run_op_program::{load_fixture, Program};
+use super::util::RollupConfig;
+
+/// The logging target to use for [tracing].
+const TARGET: &str = "serve-preimages";
+
+/// The file descriptor the hints are read from.
+const HINT_READ_FD: i32 = 3;
+/// The file descriptor the hint acknowledgements are written to.
+const HINT_WRITE_FD: i32 = 4;
+/// The file descriptor the preimage keys are read from.
+const PREIMAGE_READ_FD: i32 = 5;
+/// The file descriptor the preimages are written to.
+const PREIMAGE_WRITE_FD: i32 = 6;
+
+/// The L2 chain ID which tells the client to read the chain configuration from the local
+/// preimages rather than its embedded chain configurations.
+const CUSTOM_CHAIN_ID: u64 = u64::MAX;
+
+/// CLI arguments for the `serve-preimages` subcommand of `opfp`.
+///
+/// Serves the witness data of a fixture over the preimage oracle protocol, in place of the
+/// op-program host running with `--server`. The hint and preimage channels are read from
+/// file descriptors 3 and 5 and written to file descriptors 4 and 6, as wired up by Cannon
+/// and asterisc.
+#[derive(Parser, Clone, Debug)]
+pub struct ServePreimages {
+    /// Path to the fixture file
+    #[clap(short, long, help = "Path to the fixture file")]
+    pub fixture: PathBuf,
+    /// The fault proof program served, which determines the layout of the local preimages
+    #[clap(
+        long,
+        value_enum,
+        default_value_t = Program::OpProgram,
+        help = "Fault proof program served, which determines the layout of the local preimages"
+    )]
+    pub program: Program,
+    /// Optional path to write the requested preimage keys to
+    #[clap(
+        long,
+        help = "Path to write the requested preimage keys to, one per line as they are requested"
+    )]
+    pub requests: Option<PathBuf>,
+    /// Verbosity level (0-4)
+    #[arg(long, short, help = "Verbosity level (0-4)", action = ArgAction::Count)]
+    pub v: u8,
+}
+
+impl ServePreimages {
+    /// Runs the `serve-preimages` subcommand.
+    pub async fn run(&self) -> Result<()> {
+        let mut server = PreimageServer::new(load_fixture(&self.fixture)?, self.program)?;
+        // The keys are written as they are requested, as the VM interrupts the server once
+        // the program exits rather than closing the channels.
+        if let Some(path) = &self.requests {
+            server.requests_log = Some(File::create(path)?);
+        }
+        info!(
+            target: TARGET,
+            "Serving {} preimages from {:?}",
+            server.preimages.len(),
+            self.fixture
+        );
+
+        // SAFETY: the file descriptors are opened by the VM running the client, and are not
+        // used anywhere else in this process.
+        let (hint_read, hint_write, preimage_read, preimage_write) = unsafe {
+            (
+                File::from_raw_fd(HINT_READ_FD),
+                File::from_raw_fd(HINT_WRITE_FD),
+                File::from_raw_fd(PREIMAGE_READ_FD),
+                File::from_raw_fd(PREIMAGE_WRITE_FD),
+            )
+        };
+
+        std::thread::scope(|scope| {
+            let hints = scope.spawn(|| serve_hints(hint_read, hint_write));
+            let preimages = server.serve(preimage_read, preimage_write);
+            hints.join().map_err(|_| eyre!("Hint channel panicked"))??;
+            preimages
+        })
+    }
+}
+
+/// A preimage oracle server, serving the witness data of a fixture along with the local
+/// preimages bootstrapped from its inputs.
+#[derive(Debug)]
+pub struct PreimageServer {
+    /// The preimages to serve, by preimage key.
+    preimages: BTreeMap<B256, Bytes>,
+    /// The keys of the preimages requested so far.
+    requested: BTreeSet<B256>,
+    /// The optional file each newly requested key is written to, including missing keys.
+    pub requests_log: Option<File>,
+}
+
+impl PreimageServer {
+    /// Creates a new [PreimageServer] for the given fixture, serving the local preimages in
+    /// the layout read by the given program.
+    ///
+    /// The client reads the chain configuration of named chains from its embedded
+    /// configurations, so only their chain ID is served, resolved from the superchain
+    /// registry. For unnamed chains, the op-program reads the L2 chain config and rollup
+    /// config after a custom chain ID, while kona-client reads its rollup config after the L2
+    /// chain ID.
+    pub fn new(fixture: FaultProofFixture, program: Program) -> Result<Self> {
+        let inputs = &fixture.inputs;
+        let mut local = vec![
+            Bytes::copy_from_slice(inputs.l1_head.as_slice()),
+            Bytes::copy_from_slice(inputs.l2_output_root.as_slice()),
+            Bytes::copy_from_slice(inputs.l2_claim.as_slice()),
+            Bytes::copy_from_slice(&inputs.l2_block_number.to_be_bytes()),
+        ];
+        match (&inputs.chain_definition, program) {
+            (ChainDefinition::Named(name), _) => {
+                local.push(Bytes::copy_from_slice(&named_chain_id(name)?.to_be_bytes()));
+            }
+            (ChainDefinition::Unnamed(rollup_config, genesis), Program::OpProgram) => {
+                let rollup_config: RollupConfig = rollup_config.into();
+                local.push(Bytes::copy_from_slice(&CUSTOM_CHAIN_ID.to_be_bytes()));
+                local.push(Bytes::from(serde_json::to_vec(&genesis.config)?));
+                local.push(Bytes::from(serde_json::to_vec(&rollup_config)?));
+            }
+            (ChainDefinition::Unnamed(rollup_config, _), Program::Kona) => {
+                local.push(Bytes::copy_from_slice(
+                    &rollup_config.l2_chain_id.to_be_bytes(),
+                ));
+                local.push(Bytes::from(serde_json::to_vec(rollup_config)?));
+            }
+        }
+
+        let mut preimages = fixture.witness_data;
+        for (index, value) in (1..).zip(local) {
+            preimages.insert(PreimageKey::new_local(index).into(), value);
+        }
+        Ok(Self {
+            preimages,
+            requested: BTreeSet::new(),
+            requests_log: None,
+        })
+    }
+
+    /// Returns the preimage of the given key, if it is known.
+    pub fn get(&self, key: &B256) -> Option<&Bytes> {
+        self.preimages.get(key)
+    }
+
+    /// Returns the keys of the preimages requested so far, including any missing keys.
+    pub fn requested(&self) -> &BTreeSet<B256> {
+        &self.requested
+    }
+
+    /// Serves preimage requests until the client closes the channel. Each request is a 32
+    /// byte preimage key, answered with the big-endian `u64` length of the preimage followed
+    /// by the preimage itself.
+    pub fn serve(&mut self, mut reader: impl Read, mut writer: impl Write) -> Result<()> {
+        let mut key = B256::ZERO;
+        loop {
+            match reader.read_exact(key.as_mut_slice()) {
+                Ok(()) => {}
+                Err(e) if e.kind() == ErrorKind::UnexpectedEof => return Ok(()),
+                Err(e) => return Err(e.into()),
+            }
+            if self.requested.insert(key) {
+                if let Some(log) = &mut self.requests_log {
+                    writeln!(log, "{}", key)?;
+                }
+            }
+
+            let Some(preimage) = self.get(&key) else {
+                error!(target: TARGET, "Missing preimage for key {}", key);
+                return Err(eyre!("Missing preimage for key {}", key));
+            };
+            debug!(
+                target: TARGET,
+                "Serving preimage for key {} ({} bytes)",
+                key,
+                preimage.len()
+            );
+
+            writer.write_all(&(preimage.len() as u64).to_be_bytes())?;
+            writer.write_all(preimage)?;
+            writer.flush()?;
+        }
+    }
+}
+
+/// Acknowledges hints until the client closes the channel. Each hint is a big-endian `u32`
+/// length followed by the hint itself, and is acknowledged with a single byte. The hints are
+/// only logged, as every preimage is already known.
+pub fn serve_hints(mut reader: impl Read, mut writer: impl Write) -> Result<()> {
+    let mut length = [0u8; 4];
+    loop {
+        match reader.read_exact(&mut length) {
+            Ok(()) => {}
+            Err(e) if e.kind() == ErrorKind::UnexpectedEof => return Ok(()),
+            Err(e) => return Err(e.into()),
+        }
+
+        let mut hint = vec![0u8; u32::from_be_bytes(length) as usize];
+        reader.read_exact(&mut hint)?;
+        trace!(target: TARGET, "Received hint {}", String::from_utf8_lossy(&hint));
+
+        writer.write_all(&[0])?;
+        writer.flush()?;
+    }
+}
+
+/// Resolves the L2 chain ID of a named chain from the superchain registry. Chains are named
+/// as by the op-program `--network` flag, i.e. `<chain>-<superchain>`.
+fn named_chain_id(name: &str) -> Result<u64> {
+    superchain_registry::CHAINS
+        .iter()
+        .find(|chain| network_name(&chain.identifier).as_deref() == Some(name))
+        .map(|chain| chain.chain_id)
+        .ok_or_else(|| eyre!("Unknown chain name {:?}", name))
+}
+
+/// Returns the network name of a superchain registry chain identifier, e.g. `op-mainnet` for
+/// `mainnet/op`.
+fn network_name(identifier: &str) -> Option<String> {
+    let (superchain, chain) = identifier.split_once('/')?;
+    Some(format!("{}-{}", chain, superchain))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fp_test_fixtures::Genesis;
+
+    #[test]
+    fn test_serve_preimages() {
+        let mut fixture = FaultProofFixture::default();
+        fixture.inputs.l2_block_number = 42;
+        let mut rollup_config = superchain_primitives::RollupConfig::default();
+        rollup_config.genesis.system_config = Some(Default::default());
+        fixture.inputs.chain_definition =
+            ChainDefinition::Unnamed(rollup_config, Genesis::default());
+        let key = B256::from([2; 32]);
+        fixture
+            .witness_data
+            .insert(key, Bytes::from_static(&[1, 2, 3]));
+        let mut server =
+            PreimageServer::new(fixture, Program::OpProgram).expect("failed to create server");
+        let log_path =
+            std::env::temp_dir().join(format!("opfp-requests-test-{}", std::process::id()));
+        server.requests_log = Some(File::create(&log_path).unwrap());
+
+        let mut requests = Vec::new();
+        requests.extend_from_slice(key.as_slice());
+        requests.extend_from_slice(B256::from(PreimageKey::new_local(4)).as_slice());
+        let mut responses = Vec::new();
+        server
+            .serve(requests.as_slice(), &mut responses)
+            .expect("failed to serve preimages");
+
+        let mut expected = Vec::new();
+        expected.extend_from_slice(&3u64.to_be_bytes());
+        expected.extend_from_slice(&[1, 2, 3]);
+        expected.extend_from_slice(&8u64.to_be_bytes());
+        expected.extend_from_slice(&42u64.to_be_bytes());
+        assert_eq!(responses, expected);
+
+        let missing = B256::from([4; 32]);
+        assert!(server.serve(missing.as_slice(), &mut Vec::new()).is_err());
+        assert_eq!(
+            server.requested().iter().collect::<Vec<_>>(),
+            vec![&B256::from(PreimageKey::new_local(4)), &key, &missing]
+        );
+
+        // Every key is logged in request order, before the server stops.
+        let log = std::fs::read_to_string(&log_path).unwrap();
+        std::fs::remove_file(&log_path).unwrap();
+        assert_eq!(
+            log,
+            format!(
+                "{}\n{}\n{}\n",
+                key,
+                B256::from(PreimageKey::new_local(4)),
+                missing
+            )
+        );
+    }
+
+    #[test]
+    fn test_local_preimage_layouts() {
+        let mut fixture = FaultProofFixture::default();
+        let mut rollup_config = superchain_primitives::RollupConfig {
+            l2_chain_id: 2151908,
+            ..Default::default()
+        };
+        rollup_config.genesis.system_config = Some(Default::default());
+        fixture.inputs.chain_definition =
+            ChainDefinition::Unnamed(rollup_config.clone(), Genesis::default());
+        let local = |index| B256::from(PreimageKey::new_local(index));
+
+        let op_program = PreimageServer::new(fixture.clone(), Program::OpProgram).unwrap();
+        assert_eq!(
+            op_program.get(&local(5)).unwrap().as_ref(),
+            CUSTOM_CHAIN_ID.to_be_bytes()
+        );
+        assert!(op_program.get(&local(7)).is_some());
+
+        let kona = PreimageServer::new(fixture, Program::Kona).unwrap();
+        assert_eq!(
+            kona.get(&local(5)).unwrap().as_ref(),
+            2151908u64.to_be_bytes()
+        );
+        let served: superchain_primitives::RollupConfig =
+            serde_json::from_slice(kona.get(&local(6)).unwrap()).unwrap();
+        assert_eq!(served, rollup_config);
+        assert!(kona.get(&local(7)).is_none());
+    }
+
+    #[test]
+    fn test_named_chain_id() {
+        assert_eq!(network_name("mainnet/op").as_deref(), Some("op-mainnet"));
+        assert_eq!(
+            network_name("sepolia/base").as_deref(),
+            Some("base-sepolia")
+        );
+        assert_eq!(network_name("op"), None);
+
+        for chain in superchain_registry::CHAINS.iter() {
+            let name = network_name(&chain.identifier).expect("invalid chain identifier");
+            assert_eq!(named_chain_id(&name).unwrap(), chain.chain_id);
+        }
+
+        let mut fixture = FaultProofFixture::default();
+        fixture.inputs.chain_definition = ChainDefinition::Named("unknown-mainnet".to_string());
+        assert!(PreimageServer::new(fixture, Program::Kona).is_err());
+    }
+
+    #[test]
+    fn test_serve_hints() {
+        let mut requests = Vec::new();
+        for hint in ["l1-block-header 0x01", "l2-code 0x02"] {
+            requests.extend_from_slice(&(hint.len() as u32).to_be_bytes());
+            requests.extend_from_slice(hint.as_bytes());
+        }
+        let mut responses = Vec::new();
+        serve_hints(requests.as_slice(), &mut responses).expect("failed to serve hints");
+        assert_eq!(responses, vec![0, 0]);
+    }
+}
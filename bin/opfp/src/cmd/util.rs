@@ -1,11 +1,15 @@
 use alloy_eips::eip1559::BaseFeeParams;
-use alloy_primitives::{Address, B256};
+use alloy_primitives::{keccak256, Address, B256};
 use alloy_provider::{Provider, ReqwestProvider};
-use byteorder::{BigEndian, ReadBytesExt};
-use color_eyre::Result;
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+use color_eyre::{eyre::eyre, Result};
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+use fp_test_fixtures::FaultProofStatus;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
-use std::io::{Cursor, Read};
+use std::collections::{BTreeMap, HashMap};
+use std::io::{Cursor, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitStatus;
 
 /// Represents the response containing the l2 output.
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -285,10 +289,88 @@ pub struct SystemConfig {
     pub gas_limit: u64,
 }
 
-#[derive(Clone, Debug, Default, PartialEq, Eq)]
+/// A Cannon VM state, prefixed by the version of its encoding.
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub struct VersionedState {
     pub version: u8,
-    pub single_threaded_fpvmstate: SingleThreadedFPVMState,
+    pub state: FPVMState,
+}
+
+/// The Cannon VM state, in the layout of its version.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum FPVMState {
+    /// The state of the single-threaded 32-bit VM.
+    SingleThreaded(SingleThreadedFPVMState),
+    /// The state of the multi-threaded 32-bit VM.
+    MultiThreaded(MultiThreadedFPVMState),
+    /// The state of the multi-threaded 64-bit VM.
+    MultiThreaded64(MultiThreadedFPVMState),
+}
+
+/// The versions of the Cannon state encoding.
+pub mod state_version {
+    /// The original single-threaded 32-bit state.
+    pub const SINGLE_THREADED: u8 = 0;
+    /// The original multi-threaded 32-bit state.
+    pub const MULTI_THREADED: u8 = 1;
+    /// The single-threaded 32-bit state, with the same layout as [SINGLE_THREADED].
+    pub const SINGLE_THREADED_2: u8 = 2;
+    /// The original multi-threaded 64-bit state.
+    pub const MULTI_THREADED_64: u8 = 3;
+    /// The multi-threaded 64-bit state, with the same layout as [MULTI_THREADED_64].
+    pub const MULTI_THREADED_64_V2: u8 = 4;
+    /// The multi-threaded 32-bit state, with the same layout as [MULTI_THREADED].
+    pub const MULTI_THREADED_V2: u8 = 5;
+    /// The multi-threaded 64-bit state, with the LL reservation in the state.
+    pub const MULTI_THREADED_64_V3: u8 = 6;
+    /// The multi-threaded 64-bit state, with the same layout as [MULTI_THREADED_64_V3].
+    pub const MULTI_THREADED_64_V4: u8 = 7;
+}
+
+/// The layout of a Cannon state encoding.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StateLayout {
+    /// The layout of the single-threaded 32-bit VM.
+    SingleThreaded,
+    /// The layout of the multi-threaded VMs.
+    MultiThreaded(MultiThreadedLayout),
+}
+
+/// The layout of a multi-threaded Cannon state encoding.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MultiThreadedLayout {
+    /// The width of the words of the VM.
+    pub word: WordSize,
+    /// Whether the LL reservation status, address and owner thread are encoded after the
+    /// heap.
+    pub ll_reservation: bool,
+}
+
+impl StateLayout {
+    /// Returns the layout of the given state version, failing on unknown versions.
+    pub fn of_version(version: u8) -> Result<Self> {
+        let multi_threaded = |word, ll_reservation| {
+            Self::MultiThreaded(MultiThreadedLayout {
+                word,
+                ll_reservation,
+            })
+        };
+        Ok(match version {
+            state_version::SINGLE_THREADED | state_version::SINGLE_THREADED_2 => {
+                Self::SingleThreaded
+            }
+            state_version::MULTI_THREADED | state_version::MULTI_THREADED_V2 => {
+                multi_threaded(WordSize::Bits32, false)
+            }
+            state_version::MULTI_THREADED_64 | state_version::MULTI_THREADED_64_V2 => {
+                multi_threaded(WordSize::Bits64, false)
+            }
+            state_version::MULTI_THREADED_64_V3 | state_version::MULTI_THREADED_64_V4 => {
+                multi_threaded(WordSize::Bits64, true)
+            }
+            _ => return Err(eyre!("unsupported Cannon state version {}", version)),
+        })
+    }
 }
 
 #[derive(Clone, Debug, Default, PartialEq, Eq)]
@@ -306,9 +388,109 @@ pub struct SingleThreadedFPVMState {
     pub last_hint: Vec<u8>,
 }
 
+// https://github.com/ethereum-optimism/optimism/blob/develop/cannon/mipsevm/multithreaded/state.go
+// Words are 4 bytes wide in the 32-bit VM and 8 bytes wide in the 64-bit VM, and are stored
+// widened to 64 bits.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct MultiThreadedFPVMState {
+    pub memory: Memory,
+    pub preimage_key: B256,
+    pub preimage_offset: u64,
+    pub heap: u64,
+    pub ll_reservation_status: u8,
+    pub ll_address: u64,
+    pub ll_owner_thread: u64,
+    pub exit_code: u8,
+    pub exited: bool,
+    pub step: u64,
+    pub steps_since_last_context_switch: u64,
+    pub wakeup: u64,
+    pub traverse_right: bool,
+    pub left_thread_stack: Vec<ThreadState>,
+    pub right_thread_stack: Vec<ThreadState>,
+    pub next_thread_id: u64,
+    pub last_hint: Vec<u8>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ThreadState {
+    pub thread_id: u64,
+    pub exit_code: u8,
+    pub exited: bool,
+    pub futex_addr: u64,
+    pub futex_val: u64,
+    pub futex_timeout_step: u64,
+    pub pc: u64,
+    pub next_pc: u64,
+    pub lo: u64,
+    pub hi: u64,
+    pub registers: [u64; 32],
+}
+
+/// The width of a machine word of the VM.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WordSize {
+    /// 4 byte words, used by the 32-bit VMs.
+    Bits32,
+    /// 8 byte words, used by the 64-bit VMs.
+    Bits64,
+}
+
+impl WordSize {
+    /// Reads a big-endian word, widened to 64 bits.
+    fn read<T>(self, cursor: &mut Cursor<T>) -> Result<u64>
+    where
+        T: AsRef<[u8]>,
+    {
+        Ok(match self {
+            WordSize::Bits32 => cursor.read_u32::<BigEndian>()?.into(),
+            WordSize::Bits64 => cursor.read_u64::<BigEndian>()?,
+        })
+    }
+
+    /// Writes a big-endian word, failing if the value does not fit in the word.
+    fn write<W>(self, writer: &mut W, value: u64) -> Result<()>
+    where
+        W: Write,
+    {
+        match self {
+            WordSize::Bits32 => writer.write_u32::<BigEndian>(value.try_into()?)?,
+            WordSize::Bits64 => writer.write_u64::<BigEndian>(value)?,
+        }
+        Ok(())
+    }
+
+    /// Returns the number of bits in a word, which is also the width of a memory address.
+    pub fn bits(self) -> u32 {
+        match self {
+            WordSize::Bits32 => 32,
+            WordSize::Bits64 => 64,
+        }
+    }
+}
+
+/// The log2 of the size of a memory page, in bytes.
+const PAGE_ADDR_BITS: u32 = 12;
+/// The log2 of the size of a leaf of the memory merkle tree, in bytes.
+const LEAF_ADDR_BITS: u32 = 5;
+/// The depth of the merkle tree of a single memory page.
+const PAGE_TREE_DEPTH: u32 = PAGE_ADDR_BITS - LEAF_ADDR_BITS;
+
+/// The state hash status byte of a VM which exited with status 0.
+const VM_STATUS_VALID: u8 = 0;
+/// The messages logged by the op-program host and kona-client when rejecting the claim.
+const INVALID_CLAIM_MESSAGES: [&str; 2] = ["Claim is invalid", "Invalid claim"];
+
+/// The state hash status byte of a VM which exited with status 1.
+const VM_STATUS_INVALID: u8 = 1;
+/// The state hash status byte of a VM which exited with any other status.
+const VM_STATUS_PANIC: u8 = 2;
+/// The state hash status byte of a VM which has not exited.
+const VM_STATUS_UNFINISHED: u8 = 3;
+
 #[derive(Clone, Debug, Default, PartialEq, Eq)]
 pub struct Memory {
-    pub pages: HashMap<u32, [u8; 4096]>,
+    pub pages: HashMap<u64, [u8; 4096]>,
 }
 
 #[derive(Clone, Debug, Default, PartialEq, Eq)]
@@ -319,33 +501,272 @@ pub struct CpuScalars {
     pub hi: u32,
 }
 
+impl VersionedState {
+    /// Returns the [FaultProofStatus] of the program, as encoded in the VM state.
+    pub fn status(&self) -> FaultProofStatus {
+        let (exited, exit_code) = match &self.state {
+            FPVMState::SingleThreaded(state) => (state.exited, state.exit_code),
+            FPVMState::MultiThreaded(state) | FPVMState::MultiThreaded64(state) => {
+                (state.exited, state.exit_code)
+            }
+        };
+        if !exited {
+            return FaultProofStatus::Unfinished;
+        }
+        status_from_exit_code(exit_code)
+    }
+
+    /// Returns the number of instructions executed by the VM.
+    pub fn step(&self) -> u64 {
+        match &self.state {
+            FPVMState::SingleThreaded(state) => state.step,
+            FPVMState::MultiThreaded(state) | FPVMState::MultiThreaded64(state) => state.step,
+        }
+    }
+
+    /// Reads a state file, decompressing it if its name ends with `.gz` as Cannon does.
+    pub fn read_file(path: &Path) -> Result<Self> {
+        let data = std::fs::read(path)?;
+        let data = if path.extension().is_some_and(|ext| ext == "gz") {
+            let mut decompressed = Vec::new();
+            GzDecoder::new(data.as_slice()).read_to_end(&mut decompressed)?;
+            decompressed
+        } else {
+            data
+        };
+        Self::try_from(data).map_err(|e| eyre!(e))
+    }
+
+    /// Writes the state to a file, compressing it if its name ends with `.gz` as Cannon does.
+    pub fn write_file(&self, path: &Path) -> Result<()> {
+        let data = self.to_bytes()?;
+        let mut file = std::fs::File::create(path)?;
+        if path.extension().is_some_and(|ext| ext == "gz") {
+            let mut encoder = GzEncoder::new(file, Compression::default());
+            encoder.write_all(&data)?;
+            encoder.finish()?;
+        } else {
+            file.write_all(&data)?;
+        }
+        Ok(())
+    }
+
+    /// Encodes the state in the layout of its version.
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        let mut buffer = Vec::new();
+        self.encode(&mut buffer)?;
+        Ok(buffer)
+    }
+
+    /// Returns the word size of the VM.
+    pub fn word_size(&self) -> WordSize {
+        match &self.state {
+            FPVMState::MultiThreaded64(_) => WordSize::Bits64,
+            _ => WordSize::Bits32,
+        }
+    }
+
+    /// Returns the layout of the state, failing if its version is unknown or does not match
+    /// the state.
+    pub fn layout(&self) -> Result<StateLayout> {
+        let layout = StateLayout::of_version(self.version)?;
+        let matches_state = match (&self.state, layout) {
+            (FPVMState::SingleThreaded(_), StateLayout::SingleThreaded) => true,
+            (
+                FPVMState::MultiThreaded(_) | FPVMState::MultiThreaded64(_),
+                StateLayout::MultiThreaded(layout),
+            ) => layout.word == self.word_size(),
+            _ => false,
+        };
+        if !matches_state {
+            return Err(eyre!(
+                "Cannon state version {} does not match the layout of the state",
+                self.version
+            ));
+        }
+        Ok(layout)
+    }
+
+    /// Returns the memory of the VM.
+    pub fn memory(&self) -> &Memory {
+        match &self.state {
+            FPVMState::SingleThreaded(state) => &state.memory,
+            FPVMState::MultiThreaded(state) | FPVMState::MultiThreaded64(state) => &state.memory,
+        }
+    }
+
+    /// Encodes the state witness, which commits to the memory by its merkle root and, for the
+    /// multi-threaded VMs, to the thread stacks by their hash chains.
+    pub fn witness(&self) -> Result<Vec<u8>> {
+        let word = self.word_size();
+        let layout = self.layout()?;
+        let mut witness = Vec::new();
+        match &self.state {
+            FPVMState::SingleThreaded(state) => {
+                witness.write_all(state.memory.merkle_root(word).as_slice())?;
+                witness.write_all(state.preimage_key.as_slice())?;
+                witness.write_u32::<BigEndian>(state.perimage_offset)?;
+                state.cpu.encode(&mut witness)?;
+                witness.write_u32::<BigEndian>(state.heap)?;
+                witness.write_u8(state.exit_code)?;
+                witness.write_u8(state.exited.into())?;
+                witness.write_u64::<BigEndian>(state.step)?;
+                for register in state.registers {
+                    witness.write_u32::<BigEndian>(register)?;
+                }
+            }
+            FPVMState::MultiThreaded(state) | FPVMState::MultiThreaded64(state) => {
+                witness.write_all(state.memory.merkle_root(word).as_slice())?;
+                witness.write_all(state.preimage_key.as_slice())?;
+                word.write(&mut witness, state.preimage_offset)?;
+                word.write(&mut witness, state.heap)?;
+                if matches!(layout, StateLayout::MultiThreaded(layout) if layout.ll_reservation) {
+                    witness.write_u8(state.ll_reservation_status)?;
+                    word.write(&mut witness, state.ll_address)?;
+                    word.write(&mut witness, state.ll_owner_thread)?;
+                }
+                witness.write_u8(state.exit_code)?;
+                witness.write_u8(state.exited.into())?;
+                witness.write_u64::<BigEndian>(state.step)?;
+                witness.write_u64::<BigEndian>(state.steps_since_last_context_switch)?;
+                word.write(&mut witness, state.wakeup)?;
+                witness.write_u8(state.traverse_right.into())?;
+                for stack in [&state.left_thread_stack, &state.right_thread_stack] {
+                    witness.write_all(thread_stack_root(stack, word)?.as_slice())?;
+                }
+                word.write(&mut witness, state.next_thread_id)?;
+            }
+        }
+        Ok(witness)
+    }
+
+    /// Returns the state hash Cannon commits to: the hash of the state witness, with its
+    /// first byte replaced by the VM status.
+    pub fn state_hash(&self) -> Result<B256> {
+        let mut hash = keccak256(self.witness()?);
+        hash[0] = match self.status() {
+            FaultProofStatus::Valid => VM_STATUS_VALID,
+            FaultProofStatus::Invalid => VM_STATUS_INVALID,
+            FaultProofStatus::Unfinished => VM_STATUS_UNFINISHED,
+            _ => VM_STATUS_PANIC,
+        };
+        Ok(hash)
+    }
+}
+
+/// Returns the root of a thread stack, chaining the hash of each thread's encoding from the
+/// bottom of the stack to its top onto the root of the empty stack.
+fn thread_stack_root(stack: &[ThreadState], word: WordSize) -> Result<B256> {
+    let mut root = hash_pair(B256::ZERO, B256::ZERO);
+    for thread in stack {
+        let mut encoded = Vec::new();
+        thread.encode(&mut encoded, word)?;
+        root = hash_pair(root, keccak256(encoded));
+    }
+    Ok(root)
+}
+
+/// Maps the exit code of the fault proof program within a VM to a [FaultProofStatus],
+/// following the VM status semantics used by Cannon.
+pub fn status_from_exit_code(exit_code: u8) -> FaultProofStatus {
+    match exit_code {
+        0 => FaultProofStatus::Valid,
+        1 => FaultProofStatus::Invalid,
+        _ => FaultProofStatus::Panic,
+    }
+}
+
+/// Maps the exit status of a natively executed program to a [FaultProofStatus], given the
+/// output it logged.
+///
+/// The op-program host also exits with 1 on any host error, e.g. a missing preimage, so 1 is
+/// only reported as [FaultProofStatus::Invalid] if the output rejects the claim, and is an
+/// error otherwise. Processes terminated by a signal are reported as
+/// [FaultProofStatus::Unknown].
+pub fn native_status(status: ExitStatus, output: &str) -> Result<FaultProofStatus> {
+    match status.code() {
+        Some(0) => Ok(FaultProofStatus::Valid),
+        Some(1)
+            if INVALID_CLAIM_MESSAGES
+                .iter()
+                .any(|message| output.contains(message)) =>
+        {
+            Ok(FaultProofStatus::Invalid)
+        }
+        Some(1) => Err(eyre!(
+            "The program exited with 1 without rejecting the claim"
+        )),
+        Some(_) => Ok(FaultProofStatus::Panic),
+        None => Ok(FaultProofStatus::Unknown),
+    }
+}
+
 trait Decodable {
     fn decode<T>(&mut self, cursor: &mut Cursor<T>) -> Result<()>
     where
         T: AsRef<[u8]>;
 }
 
+trait Encodable {
+    fn encode<W>(&self, writer: &mut W) -> Result<()>
+    where
+        W: Write;
+}
+
 impl TryFrom<Vec<u8>> for VersionedState {
     type Error = String;
 
     fn try_from(buffer: Vec<u8>) -> Result<Self, Self::Error> {
-        let mut v = VersionedState::default();
         let mut cursor = Cursor::new(buffer);
-        let result = v.decode(&mut cursor);
+        let result = VersionedState::decode_versioned(&mut cursor);
         return match result {
-            Ok(_) => Ok(v),
+            Ok(v) => Ok(v),
             Err(err) => Err(format!("invalid versioned state encoding: {err}").to_string()),
         };
     }
 }
 
-impl Decodable for VersionedState {
-    fn decode<T>(&mut self, cursor: &mut Cursor<T>) -> Result<()>
+impl VersionedState {
+    /// Decodes the state in the layout of the version in its first byte.
+    fn decode_versioned<T>(cursor: &mut Cursor<T>) -> Result<Self>
     where
         T: AsRef<[u8]>,
     {
-        self.version = cursor.read_u8()?;
-        self.single_threaded_fpvmstate.decode(cursor)
+        let version = cursor.read_u8()?;
+        let state = match StateLayout::of_version(version)? {
+            StateLayout::SingleThreaded => {
+                let mut state = SingleThreadedFPVMState::default();
+                state.decode(cursor)?;
+                FPVMState::SingleThreaded(state)
+            }
+            StateLayout::MultiThreaded(layout) => {
+                let state = MultiThreadedFPVMState::decode(cursor, layout)?;
+                match layout.word {
+                    WordSize::Bits32 => FPVMState::MultiThreaded(state),
+                    WordSize::Bits64 => FPVMState::MultiThreaded64(state),
+                }
+            }
+        };
+        Ok(Self { version, state })
+    }
+}
+
+impl Encodable for VersionedState {
+    fn encode<W>(&self, writer: &mut W) -> Result<()>
+    where
+        W: Write,
+    {
+        let layout = self.layout()?;
+
+        writer.write_u8(self.version)?;
+        match (&self.state, layout) {
+            (FPVMState::SingleThreaded(state), _) => state.encode(writer),
+            (
+                FPVMState::MultiThreaded(state) | FPVMState::MultiThreaded64(state),
+                StateLayout::MultiThreaded(layout),
+            ) => state.encode(writer, layout),
+            _ => unreachable!("the layout matches the state"),
+        }
     }
 }
 
@@ -361,10 +782,7 @@ impl Decodable for SingleThreadedFPVMState {
         self.preimage_key = B256::from(&preimage_key_buffer);
         self.perimage_offset = cursor.read_u32::<BigEndian>()?;
 
-        self.cpu.pc = cursor.read_u32::<BigEndian>()?;
-        self.cpu.next_pc = cursor.read_u32::<BigEndian>()?;
-        self.cpu.lo = cursor.read_u32::<BigEndian>()?;
-        self.cpu.hi = cursor.read_u32::<BigEndian>()?;
+        self.cpu.decode(cursor)?;
 
         self.heap = cursor.read_u32::<BigEndian>()?;
         self.exit_code = cursor.read_u8()?;
@@ -388,19 +806,208 @@ impl Decodable for SingleThreadedFPVMState {
     }
 }
 
-impl Decodable for Memory {
+impl Encodable for SingleThreadedFPVMState {
+    fn encode<W>(&self, writer: &mut W) -> Result<()>
+    where
+        W: Write,
+    {
+        self.memory.encode(writer)?;
+
+        writer.write_all(self.preimage_key.as_slice())?;
+        writer.write_u32::<BigEndian>(self.perimage_offset)?;
+
+        self.cpu.encode(writer)?;
+
+        writer.write_u32::<BigEndian>(self.heap)?;
+        writer.write_u8(self.exit_code)?;
+
+        writer.write_u8(self.exited.into())?;
+        writer.write_u64::<BigEndian>(self.step)?;
+
+        for register in self.registers {
+            writer.write_u32::<BigEndian>(register)?;
+        }
+
+        writer.write_u32::<BigEndian>(self.last_hint.len().try_into()?)?;
+        writer.write_all(&self.last_hint)?;
+
+        Ok(())
+    }
+}
+
+impl Decodable for CpuScalars {
     fn decode<T>(&mut self, cursor: &mut Cursor<T>) -> Result<()>
     where
         T: AsRef<[u8]>,
     {
-        let page_count = cursor.read_u32::<BigEndian>()?;
+        self.pc = cursor.read_u32::<BigEndian>()?;
+        self.next_pc = cursor.read_u32::<BigEndian>()?;
+        self.lo = cursor.read_u32::<BigEndian>()?;
+        self.hi = cursor.read_u32::<BigEndian>()?;
+
+        Ok(())
+    }
+}
+
+impl Encodable for CpuScalars {
+    fn encode<W>(&self, writer: &mut W) -> Result<()>
+    where
+        W: Write,
+    {
+        for value in [self.pc, self.next_pc, self.lo, self.hi] {
+            writer.write_u32::<BigEndian>(value)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl MultiThreadedFPVMState {
+    /// Decodes the state of a multi-threaded VM with the given layout.
+    fn decode<T>(cursor: &mut Cursor<T>, layout: MultiThreadedLayout) -> Result<Self>
+    where
+        T: AsRef<[u8]>,
+    {
+        let word = layout.word;
+        let mut state = Self::default();
+        state.memory.decode_words(cursor, word)?;
+
+        let mut preimage_key_buffer: [u8; 32] = [0; 32];
+        cursor.read_exact(&mut preimage_key_buffer)?;
+        state.preimage_key = B256::from(&preimage_key_buffer);
+        state.preimage_offset = word.read(cursor)?;
+        state.heap = word.read(cursor)?;
+        if layout.ll_reservation {
+            state.ll_reservation_status = cursor.read_u8()?;
+            state.ll_address = word.read(cursor)?;
+            state.ll_owner_thread = word.read(cursor)?;
+        }
+        state.exit_code = cursor.read_u8()?;
+        state.exited = cursor.read_u8()? != 0;
+        state.step = cursor.read_u64::<BigEndian>()?;
+        state.steps_since_last_context_switch = cursor.read_u64::<BigEndian>()?;
+        state.wakeup = word.read(cursor)?;
+        state.traverse_right = cursor.read_u8()? != 0;
+
+        for stack in [&mut state.left_thread_stack, &mut state.right_thread_stack] {
+            let thread_count = word.read(cursor)?;
+            for _i in 0..thread_count {
+                stack.push(ThreadState::decode(cursor, word)?);
+            }
+        }
+
+        state.next_thread_id = word.read(cursor)?;
+
+        let last_hint_len = cursor.read_u32::<BigEndian>()?;
+        if last_hint_len > 0 {
+            let mut slice = vec![0; last_hint_len.try_into()?];
+            cursor.read_exact(&mut slice)?;
+
+            state.last_hint = slice;
+        }
+
+        Ok(state)
+    }
+
+    /// Encodes the state of a multi-threaded VM with the given layout.
+    fn encode<W>(&self, writer: &mut W, layout: MultiThreadedLayout) -> Result<()>
+    where
+        W: Write,
+    {
+        let word = layout.word;
+        self.memory.encode_words(writer, word)?;
+
+        writer.write_all(self.preimage_key.as_slice())?;
+        word.write(writer, self.preimage_offset)?;
+        word.write(writer, self.heap)?;
+        if layout.ll_reservation {
+            writer.write_u8(self.ll_reservation_status)?;
+            word.write(writer, self.ll_address)?;
+            word.write(writer, self.ll_owner_thread)?;
+        }
+        writer.write_u8(self.exit_code)?;
+        writer.write_u8(self.exited.into())?;
+        writer.write_u64::<BigEndian>(self.step)?;
+        writer.write_u64::<BigEndian>(self.steps_since_last_context_switch)?;
+        word.write(writer, self.wakeup)?;
+        writer.write_u8(self.traverse_right.into())?;
+
+        for stack in [&self.left_thread_stack, &self.right_thread_stack] {
+            word.write(writer, stack.len() as u64)?;
+            for thread in stack {
+                thread.encode(writer, word)?;
+            }
+        }
+
+        word.write(writer, self.next_thread_id)?;
+
+        writer.write_u32::<BigEndian>(self.last_hint.len().try_into()?)?;
+        writer.write_all(&self.last_hint)?;
+
+        Ok(())
+    }
+}
+
+impl ThreadState {
+    /// Decodes the state of a thread of a multi-threaded VM with the given word size.
+    fn decode<T>(cursor: &mut Cursor<T>, word: WordSize) -> Result<Self>
+    where
+        T: AsRef<[u8]>,
+    {
+        let mut thread = Self {
+            thread_id: word.read(cursor)?,
+            exit_code: cursor.read_u8()?,
+            exited: cursor.read_u8()? != 0,
+            futex_addr: word.read(cursor)?,
+            futex_val: word.read(cursor)?,
+            futex_timeout_step: cursor.read_u64::<BigEndian>()?,
+            pc: word.read(cursor)?,
+            next_pc: word.read(cursor)?,
+            lo: word.read(cursor)?,
+            hi: word.read(cursor)?,
+            registers: [0; 32],
+        };
+        for register in thread.registers.iter_mut() {
+            *register = word.read(cursor)?;
+        }
+        Ok(thread)
+    }
+
+    /// Encodes the state of a thread of a multi-threaded VM with the given word size.
+    fn encode<W>(&self, writer: &mut W, word: WordSize) -> Result<()>
+    where
+        W: Write,
+    {
+        word.write(writer, self.thread_id)?;
+        writer.write_u8(self.exit_code)?;
+        writer.write_u8(self.exited.into())?;
+        word.write(writer, self.futex_addr)?;
+        word.write(writer, self.futex_val)?;
+        writer.write_u64::<BigEndian>(self.futex_timeout_step)?;
+        for value in [self.pc, self.next_pc, self.lo, self.hi] {
+            word.write(writer, value)?;
+        }
+        for register in self.registers {
+            word.write(writer, register)?;
+        }
+        Ok(())
+    }
+}
+
+impl Memory {
+    /// Decodes the memory pages, with page counts and indices of the given word size.
+    fn decode_words<T>(&mut self, cursor: &mut Cursor<T>, word: WordSize) -> Result<()>
+    where
+        T: AsRef<[u8]>,
+    {
+        let page_count = word.read(cursor)?;
 
         if page_count > 0 {
             self.pages = HashMap::new();
         }
 
         for _i in 0..page_count {
-            let page_index = cursor.read_u32::<BigEndian>()?;
+            let page_index = word.read(cursor)?;
             let mut data: [u8; 4096] = [0; 4096];
             cursor.read_exact(&mut data)?;
             self.pages.insert(page_index, data);
@@ -408,14 +1015,203 @@ impl Decodable for Memory {
 
         Ok(())
     }
+
+    /// Encodes the memory pages, with page counts and indices of the given word size. Pages
+    /// are written in ascending order of their index, so the encoding is deterministic.
+    fn encode_words<W>(&self, writer: &mut W, word: WordSize) -> Result<()>
+    where
+        W: Write,
+    {
+        let mut indices = self.pages.keys().copied().collect::<Vec<_>>();
+        indices.sort_unstable();
+
+        word.write(writer, indices.len() as u64)?;
+        for index in indices {
+            word.write(writer, index)?;
+            writer.write_all(&self.pages[&index])?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Memory {
+    /// Returns the root of the binary merkle tree over the whole address space, whose leaves
+    /// are the 32 byte chunks of memory. Missing pages are zeroed.
+    pub fn merkle_root(&self, word: WordSize) -> B256 {
+        let zero_hashes = zero_hashes(word);
+        let levels = self.page_tree_levels(word, &zero_hashes);
+        levels
+            .last()
+            .and_then(|level| level.get(&0).copied())
+            .unwrap_or(zero_hashes[zero_hashes.len() - 1])
+    }
+
+    /// Returns the merkle proof of the 32 byte leaf containing the given address: the leaf
+    /// itself, followed by its siblings from the bottom of the tree to the top.
+    pub fn merkle_proof(&self, address: u64, word: WordSize) -> Vec<B256> {
+        let zero_hashes = zero_hashes(word);
+        let page_index = address >> PAGE_ADDR_BITS;
+        let page = self.pages.get(&page_index).unwrap_or(&[0; 4096]);
+
+        let mut proof = Vec::with_capacity(zero_hashes.len());
+        let mut index = ((address as usize) & ((1 << PAGE_ADDR_BITS) - 1)) >> LEAF_ADDR_BITS;
+        let mut level = page_leaves(page);
+        proof.push(level[index]);
+        for _ in 0..PAGE_TREE_DEPTH {
+            proof.push(level[index ^ 1]);
+            level = level
+                .chunks(2)
+                .map(|pair| hash_pair(pair[0], pair[1]))
+                .collect();
+            index >>= 1;
+        }
+
+        // The last level holds only the root, which is not part of the proof.
+        let levels = self.page_tree_levels(word, &zero_hashes);
+        let mut key = page_index;
+        for (depth, level) in levels[..levels.len() - 1].iter().enumerate() {
+            let sibling = level.get(&(key ^ 1)).copied();
+            proof.push(sibling.unwrap_or(zero_hashes[PAGE_TREE_DEPTH as usize + depth]));
+            key >>= 1;
+        }
+        proof
+    }
+
+    /// Returns the nodes of the merkle tree above the pages, by level from the page roots to
+    /// the root of the tree. Subtrees without any pages are omitted.
+    fn page_tree_levels(&self, word: WordSize, zero_hashes: &[B256]) -> Vec<BTreeMap<u64, B256>> {
+        let mut level = self
+            .pages
+            .iter()
+            .map(|(index, page)| (*index, page_root(page)))
+            .collect::<BTreeMap<_, _>>();
+        let mut levels = Vec::new();
+        for depth in 0..(word.bits() - PAGE_ADDR_BITS) as usize {
+            let zero_hash = zero_hashes[PAGE_TREE_DEPTH as usize + depth];
+            let mut parents = BTreeMap::new();
+            for (index, node) in &level {
+                let sibling = level.get(&(index ^ 1)).copied().unwrap_or(zero_hash);
+                let parent = if index & 1 == 0 {
+                    hash_pair(*node, sibling)
+                } else {
+                    hash_pair(sibling, *node)
+                };
+                parents.insert(index >> 1, parent);
+            }
+            levels.push(std::mem::replace(&mut level, parents));
+        }
+        levels.push(level);
+        levels
+    }
+}
+
+/// Returns the hash of two merkle tree nodes.
+fn hash_pair(left: B256, right: B256) -> B256 {
+    keccak256([left.as_slice(), right.as_slice()].concat())
+}
+
+/// Returns the roots of the empty merkle subtrees, by depth from a single zeroed leaf to the
+/// whole address space.
+fn zero_hashes(word: WordSize) -> Vec<B256> {
+    let mut hashes = vec![B256::ZERO];
+    for _ in 0..word.bits() - LEAF_ADDR_BITS {
+        let hash = hashes[hashes.len() - 1];
+        hashes.push(hash_pair(hash, hash));
+    }
+    hashes
+}
+
+/// Returns the 32 byte leaves of a memory page.
+fn page_leaves(page: &[u8; 4096]) -> Vec<B256> {
+    page.chunks(32).map(B256::from_slice).collect()
+}
+
+/// Returns the root of the merkle tree of a memory page.
+fn page_root(page: &[u8; 4096]) -> B256 {
+    let mut level = page_leaves(page);
+    while level.len() > 1 {
+        level = level
+            .chunks(2)
+            .map(|pair| hash_pair(pair[0], pair[1]))
+            .collect();
+    }
+    level[0]
+}
+
+impl Encodable for Memory {
+    fn encode<W>(&self, writer: &mut W) -> Result<()>
+    where
+        W: Write,
+    {
+        self.encode_words(writer, WordSize::Bits32)
+    }
+}
+
+impl Decodable for Memory {
+    fn decode<T>(&mut self, cursor: &mut Cursor<T>) -> Result<()>
+    where
+        T: AsRef<[u8]>,
+    {
+        self.decode_words(cursor, WordSize::Bits32)
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::cmd::util::{CpuScalars, Memory, SingleThreadedFPVMState, VersionedState};
-    use alloy_primitives::{hex, Uint, B256};
+    use crate::cmd::util::{
+        native_status, state_version, status_from_exit_code, CpuScalars, FPVMState, Memory,
+        MultiThreadedFPVMState, SingleThreadedFPVMState, ThreadState, VersionedState, WordSize,
+    };
+    use alloy_primitives::{hex, keccak256, Uint, B256};
+    use flate2::read::GzDecoder;
+    use fp_test_fixtures::FaultProofStatus;
     use std::collections::HashMap;
     use std::fs;
+    use std::io::Read;
+    use std::os::unix::process::ExitStatusExt;
+    use std::path::Path;
+    use std::process::ExitStatus;
+
+    #[test]
+    fn test_status_from_exit_code() {
+        assert_eq!(status_from_exit_code(0), FaultProofStatus::Valid);
+        assert_eq!(status_from_exit_code(1), FaultProofStatus::Invalid);
+        assert_eq!(status_from_exit_code(2), FaultProofStatus::Panic);
+        assert_eq!(status_from_exit_code(255), FaultProofStatus::Panic);
+    }
+
+    #[test]
+    fn test_native_status() {
+        let exited = |code: i32| ExitStatus::from_raw(code << 8);
+        let invalid = "CRIT [10-16|12:00:00.000] Claim is invalid err=\"invalid claim\"";
+        let kona_invalid = "ERROR Invalid claim. Expected 0x01, actual 0x02";
+        let host_error = "CRIT [10-16|12:00:00.000] Program failed err=\"not found\"";
+
+        assert_eq!(
+            native_status(exited(0), "").unwrap(),
+            FaultProofStatus::Valid
+        );
+        assert_eq!(
+            native_status(exited(1), invalid).unwrap(),
+            FaultProofStatus::Invalid
+        );
+        assert_eq!(
+            native_status(exited(1), kona_invalid).unwrap(),
+            FaultProofStatus::Invalid
+        );
+        assert!(native_status(exited(1), host_error).is_err());
+        assert!(native_status(exited(1), "").is_err());
+        assert_eq!(
+            native_status(exited(2), host_error).unwrap(),
+            FaultProofStatus::Panic
+        );
+        // Terminated by SIGKILL.
+        assert_eq!(
+            native_status(ExitStatus::from_raw(9), "").unwrap(),
+            FaultProofStatus::Unknown
+        );
+    }
 
     #[test]
     fn test_decode_versioned_state() {
@@ -463,6 +1259,316 @@ mod tests {
         let test_data_vec: Vec<u8> = test_data.to_vec();
         let v = VersionedState::try_from(test_data_vec).unwrap();
 
-        assert_eq!(v.single_threaded_fpvmstate, correct_state);
+        assert_eq!(v.state, FPVMState::SingleThreaded(correct_state));
+        assert_eq!(v.to_bytes().unwrap(), test_data.to_vec());
+    }
+
+    #[test]
+    fn test_decode_multi_threaded_state() {
+        for (version, word_size, ll_reservation) in [
+            (state_version::MULTI_THREADED, 4, false),
+            (state_version::MULTI_THREADED_64, 8, false),
+            (state_version::MULTI_THREADED_64_V2, 8, false),
+            (state_version::MULTI_THREADED_V2, 4, false),
+            (state_version::MULTI_THREADED_64_V3, 8, true),
+            (state_version::MULTI_THREADED_64_V4, 8, true),
+        ] {
+            let word = |value: u64| value.to_be_bytes()[8 - word_size..].to_vec();
+
+            let mut data = vec![version];
+            // A single memory page.
+            data.extend(word(1));
+            data.extend(word(7));
+            data.extend([0xaa; 4096]);
+            // Preimage key, offset and heap.
+            data.extend([0x02; 32]);
+            data.extend(word(3));
+            data.extend(word(0x1000));
+            // The LL reservation status, address and owner thread.
+            if ll_reservation {
+                data.push(1);
+                data.extend(word(0x2000));
+                data.extend(word(9));
+            }
+            // Exit code, exited, step, steps since the last context switch and wakeup.
+            data.extend([1, 1]);
+            data.extend(1234u64.to_be_bytes());
+            data.extend(5u64.to_be_bytes());
+            data.extend(word(0xffff));
+            // Traverse right, a single thread on the left stack and none on the right.
+            data.push(0);
+            data.extend(word(1));
+            data.extend(word(9));
+            data.extend([0, 0]);
+            data.extend(word(0x40));
+            data.extend(word(2));
+            data.extend(100u64.to_be_bytes());
+            for value in [0x10, 0x14, 1, 2] {
+                data.extend(word(value));
+            }
+            for register in 0..32 {
+                data.extend(word(register));
+            }
+            data.extend(word(0));
+            // Next thread ID and last hint.
+            data.extend(word(10));
+            data.extend(2u32.to_be_bytes());
+            data.extend([0xbe, 0xef]);
+
+            let mut registers = [0u64; 32];
+            for (i, register) in registers.iter_mut().enumerate() {
+                *register = i as u64;
+            }
+            let expected = MultiThreadedFPVMState {
+                memory: Memory {
+                    pages: HashMap::from([(7, [0xaa; 4096])]),
+                },
+                preimage_key: B256::from([0x02; 32]),
+                preimage_offset: 3,
+                heap: 0x1000,
+                ll_reservation_status: ll_reservation as u8,
+                ll_address: if ll_reservation { 0x2000 } else { 0 },
+                ll_owner_thread: if ll_reservation { 9 } else { 0 },
+                exit_code: 1,
+                exited: true,
+                step: 1234,
+                steps_since_last_context_switch: 5,
+                wakeup: 0xffff,
+                traverse_right: false,
+                left_thread_stack: vec![ThreadState {
+                    thread_id: 9,
+                    exit_code: 0,
+                    exited: false,
+                    futex_addr: 0x40,
+                    futex_val: 2,
+                    futex_timeout_step: 100,
+                    pc: 0x10,
+                    next_pc: 0x14,
+                    lo: 1,
+                    hi: 2,
+                    registers,
+                }],
+                right_thread_stack: vec![],
+                next_thread_id: 10,
+                last_hint: vec![0xbe, 0xef],
+            };
+
+            let v = VersionedState::try_from(data.clone()).unwrap();
+            assert_eq!(v.to_bytes().unwrap(), data);
+            assert_eq!(
+                v.witness().unwrap().len(),
+                32 * 4
+                    + 4 * word_size
+                    + 1
+                    + 1
+                    + 8
+                    + 8
+                    + 1
+                    + ll_reservation as usize * (1 + 2 * word_size)
+            );
+            assert_eq!(v.version, version);
+            assert_eq!(v.step(), 1234);
+            match v.state {
+                FPVMState::MultiThreaded(state) if word_size == 4 => assert_eq!(state, expected),
+                FPVMState::MultiThreaded64(state) if word_size == 8 => {
+                    assert_eq!(state, expected)
+                }
+                state => panic!("unexpected state {:?}", state),
+            }
+        }
+
+        assert!(VersionedState::try_from(vec![0x7f]).is_err());
+    }
+
+    /// Checks the state files in `testdata/cannon`, produced by a Cannon build with
+    /// `just cannon-state-vectors`, against the output of `cannon witness` for each of them.
+    #[test]
+    #[ignore = "requires the state vectors generated by `just cannon-state-vectors`"]
+    fn test_cannon_state_vectors() {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("testdata/cannon");
+        let pattern = dir.join("*.bin.gz");
+        let states = glob::glob(pattern.to_str().unwrap())
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert!(!states.is_empty(), "no state vectors in {}", dir.display());
+
+        for path in states {
+            let stem = path.to_str().unwrap().trim_end_matches(".bin.gz");
+            let state = VersionedState::read_file(&path).unwrap();
+            let witness: serde_json::Value =
+                serde_json::from_slice(&fs::read(format!("{}.json", stem)).unwrap()).unwrap();
+
+            let mut encoded = Vec::new();
+            GzDecoder::new(fs::File::open(&path).unwrap())
+                .read_to_end(&mut encoded)
+                .unwrap();
+            assert_eq!(state.to_bytes().unwrap(), encoded, "{}", path.display());
+            assert_eq!(
+                state.witness().unwrap(),
+                fs::read(format!("{}.witness", stem)).unwrap(),
+                "{}",
+                path.display()
+            );
+            assert_eq!(
+                state.state_hash().unwrap().to_string(),
+                witness["stateHash"].as_str().unwrap(),
+                "{}",
+                path.display()
+            );
+            assert_eq!(Some(state.step()), witness["step"].as_u64());
+        }
+    }
+
+    #[test]
+    fn test_memory_merkle_proof() {
+        for (word, high_page) in [(WordSize::Bits32, 0xfffff), (WordSize::Bits64, 1 << 40)] {
+            let empty = Memory::default();
+            let mut memory = Memory::default();
+            memory.pages.insert(3, [0; 4096]);
+            // Zeroed pages do not change the root.
+            assert_eq!(memory.merkle_root(word), empty.merkle_root(word));
+
+            let mut page = [0; 4096];
+            page[100] = 0xaa;
+            memory.pages.insert(5, page);
+            memory.pages.insert(high_page, [0xbb; 4096]);
+            let root = memory.merkle_root(word);
+            assert_ne!(root, empty.merkle_root(word));
+
+            let depth = (word.bits() - 5) as usize;
+            for address in [
+                5 << 12 | 100,
+                3 << 12,
+                7 << 12 | 64,
+                high_page << 12 | 0xfe0,
+            ] {
+                let proof = memory.merkle_proof(address, word);
+                assert_eq!(proof.len(), depth + 1);
+
+                let mut node = proof[0];
+                for (i, sibling) in proof[1..].iter().enumerate() {
+                    let pair = if address >> (5 + i) & 1 == 0 {
+                        [node.as_slice(), sibling.as_slice()].concat()
+                    } else {
+                        [sibling.as_slice(), node.as_slice()].concat()
+                    };
+                    node = keccak256(pair);
+                }
+                assert_eq!(node, root, "invalid proof of address {:#x}", address);
+            }
+            assert_eq!(memory.merkle_proof(5 << 12 | 100, word)[0][4], 0xaa);
+        }
+    }
+
+    #[test]
+    fn test_memory_merkle_root_cannon() {
+        // The empty subtree roots, computed independently of the memory merkleization.
+        let mut zeroes = vec![B256::ZERO];
+        for i in 0..59 {
+            zeroes.push(keccak256(
+                [zeroes[i].as_slice(), zeroes[i].as_slice()].concat(),
+            ));
+        }
+
+        // As in Cannon's memory tests, empty memory and zeroed pages have the zero root of the
+        // whole address space.
+        let mut memory = Memory::default();
+        assert_eq!(memory.merkle_root(WordSize::Bits32), zeroes[32 - 5]);
+        assert_eq!(memory.merkle_root(WordSize::Bits64), zeroes[64 - 5]);
+        memory.pages.insert(3, [0; 4096]);
+        memory.pages.insert(10, [0; 4096]);
+        assert_eq!(memory.merkle_root(WordSize::Bits32), zeroes[32 - 5]);
+
+        // Pages 3, 5 and 6 hold big-endian words 1, 42 and 123 at their start, and are
+        // combined by hand with the empty pages 0 to 7, then with the empty rest of memory.
+        let mut pages = [zeroes[12 - 5]; 8];
+        for (index, value) in [(3, 1u8), (5, 42), (6, 123)] {
+            let mut page = [0; 4096];
+            page[3] = value;
+            memory.pages.insert(index, page);
+
+            let mut leaf = B256::ZERO;
+            leaf[3] = value;
+            let mut root = leaf;
+            for zero in &zeroes[..12 - 5] {
+                root = keccak256([root.as_slice(), zero.as_slice()].concat());
+            }
+            pages[index as usize] = root;
+        }
+        let pair = |l: B256, r: B256| keccak256([l.as_slice(), r.as_slice()].concat());
+        let mut root = pair(
+            pair(pair(pages[0], pages[1]), pair(pages[2], pages[3])),
+            pair(pair(pages[4], pages[5]), pair(pages[6], pages[7])),
+        );
+        for zero in &zeroes[12 - 5 + 3..32 - 5] {
+            root = pair(root, *zero);
+        }
+        assert_eq!(memory.merkle_root(WordSize::Bits32), root);
+    }
+
+    #[test]
+    fn test_state_hash() {
+        let mut state = SingleThreadedFPVMState::default();
+        let unfinished = VersionedState {
+            version: state_version::SINGLE_THREADED_2,
+            state: FPVMState::SingleThreaded(state.clone()),
+        };
+        assert_eq!(unfinished.witness().unwrap().len(), 226);
+        let hash = unfinished.state_hash().unwrap();
+        assert_eq!(hash[0], 3);
+        assert_eq!(hash[1..], keccak256(unfinished.witness().unwrap())[1..]);
+
+        state.exited = true;
+        state.exit_code = 1;
+        let invalid = VersionedState {
+            state: FPVMState::SingleThreaded(state),
+            ..unfinished
+        };
+        assert_eq!(invalid.state_hash().unwrap()[0], 1);
+
+        let multi_threaded = VersionedState {
+            version: state_version::MULTI_THREADED_64,
+            state: FPVMState::MultiThreaded64(MultiThreadedFPVMState {
+                exited: true,
+                exit_code: 2,
+                left_thread_stack: vec![ThreadState::default()],
+                ..MultiThreadedFPVMState::default()
+            }),
+        };
+        assert_eq!(multi_threaded.state_hash().unwrap()[0], 2);
+    }
+
+    #[test]
+    fn test_state_file_round_trip() {
+        let state = VersionedState {
+            version: state_version::MULTI_THREADED,
+            state: FPVMState::MultiThreaded(MultiThreadedFPVMState {
+                step: 42,
+                left_thread_stack: vec![ThreadState::default()],
+                last_hint: vec![1, 2, 3],
+                ..MultiThreadedFPVMState::default()
+            }),
+        };
+
+        let dir = std::env::temp_dir().join(format!("opfp-state-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        for name in ["state.bin", "state.bin.gz"] {
+            let path = dir.join(name);
+            state.write_file(&path).unwrap();
+            assert_eq!(VersionedState::read_file(&path).unwrap(), state);
+        }
+        // The gzip magic bytes.
+        assert_eq!(
+            fs::read(dir.join("state.bin.gz")).unwrap()[..2],
+            [0x1f, 0x8b]
+        );
+        fs::remove_dir_all(dir).unwrap();
+
+        let mismatched = VersionedState {
+            version: state_version::SINGLE_THREADED,
+            ..state
+        };
+        assert!(mismatched.to_bytes().is_err());
     }
 }
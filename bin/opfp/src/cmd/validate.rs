@@ -0,0 +1,543 @@
+//! Validate Subcommand
+
+use alloy_primitives::{keccak256, B256, U256};
+use clap::{ArgAction, Parser};
+use color_eyre::{eyre::eyre, Result};
+use fp_test_fixtures::{ChainDefinition, FaultProofFixture, PreimageKey, PreimageKeyType};
+use sha2::{Digest, Sha256};
+use std::path::Path;
+use tracing::info;
+
+use super::run_suite::discover_fixtures;
+use super::witness::{
+    keccak_preimage, output_root, trie_account, BlockHeader, OutputRootPreimage,
+    MESSAGE_PASSER_ADDRESS,
+};
+
+/// The logging target to use for [tracing].
+const TARGET: &str = "validate";
+
+/// CLI arguments for the `validate` subcommand of `opfp`.
+#[derive(Parser, Clone, Debug)]
+pub struct Validate {
+    /// Fixture file, directory or glob pattern of the fixture files to validate
+    #[clap(
+        short,
+        long,
+        help = "Fixture file, directory or glob pattern of the fixture files to validate"
+    )]
+    pub fixtures: String,
+    /// Verbosity level (0-4)
+    #[arg(long, short, help = "Verbosity level (0-4)", action = ArgAction::Count)]
+    pub v: u8,
+}
+
+impl Validate {
+    /// Runs the `validate` subcommand.
+    pub async fn run(&self) -> Result<()> {
+        let fixtures = discover_fixtures(&self.fixtures)?;
+        if fixtures.is_empty() {
+            return Err(eyre!("No fixtures found matching {}", self.fixtures));
+        }
+
+        let mut invalid = 0;
+        let mut unverifiable = 0;
+        for fixture in &fixtures {
+            let validation = validate_fixture_file(fixture);
+            if !validation.issues.is_empty() {
+                invalid += 1;
+                println!("INVALID {}", fixture.display());
+            } else if !validation.unverifiable.is_empty() {
+                unverifiable += 1;
+                println!("UNVERIFIABLE {}", fixture.display());
+            } else {
+                println!("OK {}", fixture.display());
+            }
+            for issue in &validation.issues {
+                println!("  - {}", issue);
+            }
+            for check in &validation.unverifiable {
+                println!("  - unverifiable: {}", check);
+            }
+        }
+        info!(
+            target: TARGET,
+            "Validated {} fixtures, {} invalid, {} unverifiable",
+            fixtures.len(),
+            invalid,
+            unverifiable
+        );
+
+        if invalid > 0 {
+            return Err(eyre!(
+                "{} of {} fixtures are invalid",
+                invalid,
+                fixtures.len()
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+/// The outcome of statically validating a fixture.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Validation {
+    /// The issues which make the fixture invalid.
+    pub issues: Vec<String>,
+    /// The checks which cannot be completed from the witness data of the fixture.
+    pub unverifiable: Vec<String>,
+}
+
+/// Loads and validates the fixture at the given path.
+fn validate_fixture_file(path: &Path) -> Validation {
+    match FaultProofFixture::load(path) {
+        Ok(fixture) => validate_fixture(&fixture),
+        Err(e) => Validation {
+            issues: vec![format!("Failed to load fixture: {}", e)],
+            ..Validation::default()
+        },
+    }
+}
+
+/// Statically validates the fixture without running it.
+///
+/// The witness data is checked against the preimage key rules, the L1 and L2 head headers must
+/// be witnessed, and the agreed L2 output root must commit to the witnessed L2 head. The
+/// output root is unverifiable when neither its preimage nor the L2 head state is witnessed.
+pub fn validate_fixture(fixture: &FaultProofFixture) -> Validation {
+    let mut validation = Validation::default();
+    let issues = &mut validation.issues;
+    check_witness_keys(fixture, issues);
+    check_chain_definition(fixture, issues);
+
+    let inputs = &fixture.inputs;
+    let genesis_block = match &inputs.chain_definition {
+        ChainDefinition::Unnamed(rollup_config, _) => Some(&rollup_config.genesis),
+        ChainDefinition::Named(_) => None,
+    };
+
+    if let Some(l1_header) = witnessed_header(fixture, "L1 head", inputs.l1_head, issues) {
+        if let Some(genesis) = genesis_block {
+            if l1_header.number < genesis.l1.number {
+                issues.push(format!(
+                    "L1 head block {} is before the rollup genesis L1 block {}",
+                    l1_header.number, genesis.l1.number
+                ));
+            }
+        }
+    }
+
+    if let Some(l2_header) = witnessed_header(fixture, "L2 head", inputs.l2_head, issues) {
+        if l2_header.number >= inputs.l2_block_number {
+            issues.push(format!(
+                "L2 head block {} is not before the claimed L2 block {}",
+                l2_header.number, inputs.l2_block_number
+            ));
+        }
+        if let Some(genesis) = genesis_block {
+            if l2_header.number < genesis.l2.number {
+                issues.push(format!(
+                    "L2 head block {} is before the rollup genesis L2 block {}",
+                    l2_header.number, genesis.l2.number
+                ));
+            }
+        }
+        check_output_root(fixture, &l2_header, &mut validation);
+    }
+
+    validation
+}
+
+/// Checks that every witness key is a valid preimage key for its value.
+///
+/// Keccak256 and sha256 keys are derived from their values and are checked exactly. Blob and
+/// precompile keys commit to data that is not part of the preimage, so only the shape of their
+/// values is checked.
+fn check_witness_keys(fixture: &FaultProofFixture, issues: &mut Vec<String>) {
+    for key in fixture.unknown_witness_keys() {
+        issues.push(format!(
+            "Unknown preimage key type {} for key {}",
+            key[0], key
+        ));
+    }
+
+    for (key, value) in fixture.witnesses() {
+        let hash = match key.key_type() {
+            PreimageKeyType::Keccak256 => keccak256(value),
+            PreimageKeyType::Sha256 => B256::from_slice(&Sha256::digest(value)),
+            PreimageKeyType::Blob => {
+                if value.len() != 32 {
+                    issues.push(format!(
+                        "Blob preimage {} is {} bytes, expected a 32 byte field element",
+                        key,
+                        value.len()
+                    ));
+                }
+                continue;
+            }
+            PreimageKeyType::Precompile => {
+                if value.is_empty() {
+                    issues.push(format!("Precompile preimage {} is empty", key));
+                }
+                continue;
+            }
+            // Local and global generic keys are not derived from their values.
+            PreimageKeyType::Local | PreimageKeyType::GlobalGeneric => continue,
+        };
+
+        let expected = PreimageKey::new(hash, key.key_type());
+        if expected != key {
+            issues.push(format!(
+                "Preimage {} does not match its key, the value hashes to {}",
+                key, expected
+            ));
+        }
+    }
+}
+
+/// Checks that the chain definition is well formed.
+fn check_chain_definition(fixture: &FaultProofFixture, issues: &mut Vec<String>) {
+    let (rollup_config, genesis) = match &fixture.inputs.chain_definition {
+        ChainDefinition::Named(name) => {
+            if name.is_empty()
+                || !name
+                    .chars()
+                    .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+            {
+                issues.push(format!("Invalid chain name {:?}", name));
+            }
+            return;
+        }
+        ChainDefinition::Unnamed(rollup_config, genesis) => (rollup_config, genesis),
+    };
+
+    if rollup_config.l1_chain_id == 0 {
+        issues.push("Rollup config is missing the L1 chain ID".to_string());
+    }
+    if rollup_config.l2_chain_id != genesis.config.chain_id {
+        issues.push(format!(
+            "Rollup config L2 chain ID {} does not match the genesis chain ID {}",
+            rollup_config.l2_chain_id, genesis.config.chain_id
+        ));
+    }
+    if rollup_config.block_time == 0 {
+        issues.push("Rollup config block time is zero".to_string());
+    }
+    if rollup_config.genesis.system_config.is_none() {
+        issues.push("Rollup config is missing the genesis system config".to_string());
+    }
+    if U256::from(rollup_config.genesis.l2.number) != genesis.number {
+        issues.push(format!(
+            "Rollup config genesis L2 block {} does not match the genesis block number {}",
+            rollup_config.genesis.l2.number, genesis.number
+        ));
+    }
+    if genesis.config.optimism.is_none() {
+        issues.push("Genesis chain config is missing the optimism config".to_string());
+    }
+
+    // Hardforks must activate in order, and the forks with execution layer changes must
+    // activate at the same time in the rollup config and the genesis chain config.
+    let forks = [
+        (
+            "regolith",
+            rollup_config.regolith_time,
+            Some(genesis.config.regolith_time),
+        ),
+        (
+            "canyon",
+            rollup_config.canyon_time,
+            Some(genesis.config.canyon_time),
+        ),
+        ("delta", rollup_config.delta_time, None),
+        (
+            "ecotone",
+            rollup_config.ecotone_time,
+            Some(genesis.config.ecotone_time),
+        ),
+        (
+            "fjord",
+            rollup_config.fjord_time,
+            Some(genesis.config.fjord_time),
+        ),
+        (
+            "granite",
+            rollup_config.granite_time,
+            Some(genesis.config.granite_time),
+        ),
+        (
+            "holocene",
+            rollup_config.holocene_time,
+            Some(genesis.config.holocene_time),
+        ),
+    ];
+    let mut previous: Option<(&str, Option<u64>)> = None;
+    for (name, rollup_time, genesis_time) in forks {
+        if let Some(genesis_time) = genesis_time.filter(|&time| time != rollup_time) {
+            issues.push(format!(
+                "Rollup config {} time {:?} does not match the genesis {} time {:?}",
+                name, rollup_time, name, genesis_time
+            ));
+        }
+        if let Some((previous_name, previous_time)) = previous {
+            match (previous_time, rollup_time) {
+                (None, Some(_)) => issues.push(format!(
+                    "Rollup config activates {} without activating {}",
+                    name, previous_name
+                )),
+                (Some(previous_time), Some(time)) if time < previous_time => issues.push(format!(
+                    "Rollup config activates {} at {} before {} at {}",
+                    name, time, previous_name, previous_time
+                )),
+                _ => {}
+            }
+        }
+        previous = Some((name, rollup_time));
+    }
+}
+
+/// Returns the decoded header of the given block from the witness data, recording an issue if
+/// it is missing or malformed.
+fn witnessed_header(
+    fixture: &FaultProofFixture,
+    name: &str,
+    hash: B256,
+    issues: &mut Vec<String>,
+) -> Option<BlockHeader> {
+    let Some(data) = keccak_preimage(&fixture.witness_data, hash) else {
+        issues.push(format!(
+            "{} header {} is missing from the witness data",
+            name, hash
+        ));
+        return None;
+    };
+    match BlockHeader::decode_exact(data) {
+        Ok(header) => Some(header),
+        Err(e) => {
+            issues.push(format!("{} header {} does not decode: {}", name, hash, e));
+            None
+        }
+    }
+}
+
+/// Checks that the agreed L2 output root commits to the witnessed L2 head.
+///
+/// The witnessed output root preimage must hash to the output root and commit to the hash and
+/// state root of the L2 head, and to the storage root of the `L2ToL1MessagePasser` account if
+/// the path to the account is witnessed. Without the preimage, the output root is recomputed
+/// from the L2 head state, which is unverifiable if the path to the account is not witnessed.
+fn check_output_root(
+    fixture: &FaultProofFixture,
+    l2_header: &BlockHeader,
+    validation: &mut Validation,
+) {
+    let inputs = &fixture.inputs;
+    let preimage = match keccak_preimage(&fixture.witness_data, inputs.l2_output_root) {
+        Some(data) if keccak256(data) != inputs.l2_output_root => {
+            validation.issues.push(format!(
+                "L2 output root preimage does not hash to the L2 output root {}",
+                inputs.l2_output_root
+            ));
+            return;
+        }
+        Some(data) => match OutputRootPreimage::decode(data) {
+            Ok(preimage) => Some(preimage),
+            Err(e) => {
+                validation
+                    .issues
+                    .push(format!("L2 output root preimage does not decode: {}", e));
+                return;
+            }
+        },
+        None => None,
+    };
+    if let Some(preimage) = &preimage {
+        if preimage.block_hash != inputs.l2_head {
+            validation.issues.push(format!(
+                "L2 output root commits to block {}, not the L2 head {}",
+                preimage.block_hash, inputs.l2_head
+            ));
+        }
+        if preimage.state_root != l2_header.state_root {
+            validation.issues.push(format!(
+                "L2 output root commits to state root {}, not the L2 head state root {}",
+                preimage.state_root, l2_header.state_root
+            ));
+        }
+    }
+
+    let storage_root = match trie_account(
+        &fixture.witness_data,
+        l2_header.state_root,
+        MESSAGE_PASSER_ADDRESS,
+    ) {
+        Ok(Some(account)) => account.storage_root,
+        Ok(None) => {
+            validation
+                .issues
+                .push("L2ToL1MessagePasser account is missing from the L2 head state".to_string());
+            return;
+        }
+        // The output root preimage already commits to the L2 head.
+        Err(_) if preimage.is_some() => return,
+        Err(e) => {
+            validation.unverifiable.push(format!(
+                "Cannot recompute the L2 output root from the L2 head state: {}",
+                e
+            ));
+            return;
+        }
+    };
+
+    if let Some(preimage) = preimage {
+        if preimage.storage_root != storage_root {
+            validation.issues.push(format!(
+                "L2 output root commits to the message passer storage root {}, but the L2 head \
+                 state has {}",
+                preimage.storage_root, storage_root
+            ));
+        }
+        return;
+    }
+    let recomputed = output_root(l2_header.state_root, storage_root, inputs.l2_head);
+    if recomputed != inputs.l2_output_root {
+        validation.issues.push(format!(
+            "L2 output root {} does not match the witnessed L2 head, which commits to {}",
+            inputs.l2_output_root, recomputed
+        ));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cmd::witness::{keccak_key, tests::single_account_trie, TrieAccount};
+    use alloy_primitives::Bytes;
+    use fp_test_fixtures::{FaultProofInputs, FaultProofStatus, FIXTURE_VERSION};
+
+    fn valid_fixture() -> FaultProofFixture {
+        let storage_root = B256::from([7; 32]);
+        let (state_root, mut witness_data) = single_account_trie(
+            MESSAGE_PASSER_ADDRESS,
+            &TrieAccount {
+                storage_root,
+                ..Default::default()
+            },
+        );
+
+        let l1_header = BlockHeader {
+            number: 100,
+            ..Default::default()
+        };
+        let l2_header = BlockHeader {
+            number: 1336,
+            state_root,
+            ..Default::default()
+        };
+        for header in [&l1_header, &l2_header] {
+            witness_data.insert(
+                keccak_key(header.hash_slow()),
+                Bytes::from(alloy_rlp::encode(header)),
+            );
+        }
+        let sha256_value = Bytes::from_static(b"sha256 preimage");
+        let sha256_key = PreimageKey::new(
+            B256::from_slice(&Sha256::digest(&sha256_value)),
+            PreimageKeyType::Sha256,
+        );
+        witness_data.insert(sha256_key.into(), sha256_value);
+
+        FaultProofFixture {
+            version: FIXTURE_VERSION,
+            inputs: FaultProofInputs {
+                l1_head: l1_header.hash_slow(),
+                l2_head: l2_header.hash_slow(),
+                l2_claim: B256::from([3; 32]),
+                l2_output_root: output_root(state_root, storage_root, l2_header.hash_slow()),
+                l2_block_number: 1337,
+                chain_definition: Default::default(),
+            },
+            expected_status: FaultProofStatus::Valid,
+            witness_data,
+        }
+    }
+
+    #[test]
+    fn test_validate_fixture() {
+        let fixture = valid_fixture();
+        assert_eq!(validate_fixture(&fixture), Validation::default());
+
+        // Without the state trie, the output root cannot be recomputed.
+        let mut fixture = valid_fixture();
+        let l2_header = BlockHeader::decode_exact(
+            keccak_preimage(&fixture.witness_data, fixture.inputs.l2_head).unwrap(),
+        )
+        .unwrap();
+        fixture
+            .witness_data
+            .remove(&keccak_key(l2_header.state_root));
+        let validation = validate_fixture(&fixture);
+        assert!(validation.issues.is_empty(), "{:?}", validation.issues);
+        assert_eq!(validation.unverifiable.len(), 1);
+        assert!(validation.unverifiable[0].contains("L2 output root"));
+
+        // With the output root preimage, the output root is verified without the state trie.
+        let preimage = |state_root: B256, storage_root: B256, block_hash: B256| {
+            let mut data = vec![0u8; 32];
+            data.extend_from_slice(state_root.as_slice());
+            data.extend_from_slice(storage_root.as_slice());
+            data.extend_from_slice(block_hash.as_slice());
+            data
+        };
+        let storage_root = B256::from([7; 32]);
+        let data = preimage(l2_header.state_root, storage_root, fixture.inputs.l2_head);
+        fixture.inputs.l2_output_root = keccak256(&data);
+        fixture
+            .witness_data
+            .insert(keccak_key(keccak256(&data)), Bytes::from(data));
+        assert_eq!(validate_fixture(&fixture), Validation::default());
+
+        let data = preimage(l2_header.state_root, storage_root, B256::from([5; 32]));
+        fixture.inputs.l2_output_root = keccak256(&data);
+        fixture
+            .witness_data
+            .insert(keccak_key(keccak256(&data)), Bytes::from(data));
+        let issues = validate_fixture(&fixture).issues;
+        assert_eq!(issues.len(), 1, "{:?}", issues);
+        assert!(issues[0].contains("not the L2 head"));
+
+        // The storage root is checked against the state trie when it is witnessed.
+        let mut fixture = valid_fixture();
+        let data = preimage(
+            l2_header.state_root,
+            B256::from([8; 32]),
+            fixture.inputs.l2_head,
+        );
+        fixture.inputs.l2_output_root = keccak256(&data);
+        fixture
+            .witness_data
+            .insert(keccak_key(keccak256(&data)), Bytes::from(data));
+        let issues = validate_fixture(&fixture).issues;
+        assert_eq!(issues.len(), 1, "{:?}", issues);
+        assert!(issues[0].contains("message passer storage root"));
+
+        let mut fixture = valid_fixture();
+        fixture.inputs.l2_output_root = B256::from([4; 32]);
+        fixture.inputs.l2_block_number = 1;
+        let corrupted = *fixture.witness_data.keys().find(|key| key[0] == 4).unwrap();
+        fixture
+            .witness_data
+            .insert(corrupted, Bytes::from_static(b"corrupted"));
+        fixture
+            .witness_data
+            .remove(&keccak_key(fixture.inputs.l1_head));
+
+        let issues = validate_fixture(&fixture).issues;
+        assert_eq!(issues.len(), 4, "{:?}", issues);
+        assert!(issues[0].contains("does not match its key"));
+        assert!(issues[1].contains("L1 head header"));
+        assert!(issues[2].contains("is not before the claimed L2 block"));
+        assert!(issues[3].contains("L2 output root"));
+    }
+}
@@ -0,0 +1,744 @@
+//! Helpers for decoding the witness data of a fault proof fixture.
+
+use alloy_primitives::{address, keccak256, Address, Bloom, Bytes, B256, B64, U256};
+use alloy_rlp::{BufMut, Decodable, Encodable, Header};
+use color_eyre::{eyre::eyre, Result};
+use fp_test_fixtures::{PreimageKey, PreimageKeyType};
+use std::collections::BTreeMap;
+
+/// The address of the `L2ToL1MessagePasser` predeploy, whose storage root is committed to in
+/// the L2 output root.
+pub const MESSAGE_PASSER_ADDRESS: Address = address!("4200000000000000000000000000000000000016");
+
+/// The root hash of an empty Merkle Patricia Trie.
+pub const EMPTY_ROOT_HASH: B256 =
+    alloy_primitives::b256!("56e81f171bcc55a6ff8345e692c0f86e5b48e01b996cadc001622fb5e363b421");
+
+/// The size of a version 0 L2 output root preimage.
+pub const OUTPUT_ROOT_PREIMAGE_SIZE: usize = 128;
+
+/// Returns the preimage key of the keccak256 preimage of the given hash.
+pub fn keccak_key(hash: B256) -> B256 {
+    PreimageKey::new(hash, PreimageKeyType::Keccak256).into()
+}
+
+/// Returns the keccak256 preimage of the given hash from the witness data, if present.
+pub fn keccak_preimage(witness_data: &BTreeMap<B256, Bytes>, hash: B256) -> Option<&Bytes> {
+    witness_data.get(&keccak_key(hash))
+}
+
+/// Computes a version 0 L2 output root.
+pub fn output_root(state_root: B256, storage_root: B256, block_hash: B256) -> B256 {
+    let mut preimage = [0u8; OUTPUT_ROOT_PREIMAGE_SIZE];
+    preimage[32..64].copy_from_slice(state_root.as_slice());
+    preimage[64..96].copy_from_slice(storage_root.as_slice());
+    preimage[96..].copy_from_slice(block_hash.as_slice());
+    keccak256(preimage)
+}
+
+/// A decoded version 0 L2 output root preimage.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OutputRootPreimage {
+    /// The state root of the L2 block.
+    pub state_root: B256,
+    /// The storage root of the `L2ToL1MessagePasser` account.
+    pub storage_root: B256,
+    /// The hash of the L2 block.
+    pub block_hash: B256,
+}
+
+impl OutputRootPreimage {
+    /// Decodes a version 0 L2 output root preimage.
+    pub fn decode(data: &[u8]) -> Result<Self> {
+        if data.len() != OUTPUT_ROOT_PREIMAGE_SIZE {
+            return Err(eyre!(
+                "Invalid output root preimage length {}, expected {}",
+                data.len(),
+                OUTPUT_ROOT_PREIMAGE_SIZE
+            ));
+        }
+        if data[..32] != [0u8; 32] {
+            return Err(eyre!(
+                "Unsupported output root version {}",
+                B256::from_slice(&data[..32])
+            ));
+        }
+        Ok(Self {
+            state_root: B256::from_slice(&data[32..64]),
+            storage_root: B256::from_slice(&data[64..96]),
+            block_hash: B256::from_slice(&data[96..]),
+        })
+    }
+}
+
+/// An execution layer block header, as witnessed in its RLP encoding.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BlockHeader {
+    /// The hash of the parent block.
+    pub parent_hash: B256,
+    /// The hash of the ommers list.
+    pub ommers_hash: B256,
+    /// The address of the block's fee recipient.
+    pub beneficiary: Address,
+    /// The root of the state trie after executing the block.
+    pub state_root: B256,
+    /// The root of the transactions trie.
+    pub transactions_root: B256,
+    /// The root of the receipts trie.
+    pub receipts_root: B256,
+    /// The bloom filter of the block's logs.
+    pub logs_bloom: Bloom,
+    /// The difficulty of the block.
+    pub difficulty: U256,
+    /// The block number.
+    pub number: u64,
+    /// The gas limit of the block.
+    pub gas_limit: u64,
+    /// The gas used by the block.
+    pub gas_used: u64,
+    /// The timestamp of the block.
+    pub timestamp: u64,
+    /// The extra data of the block.
+    pub extra_data: Bytes,
+    /// The mix hash of the block.
+    pub mix_hash: B256,
+    /// The nonce of the block.
+    pub nonce: B64,
+    /// The base fee per gas of the block, from London.
+    pub base_fee_per_gas: Option<u64>,
+    /// The root of the withdrawals trie, from Shanghai.
+    pub withdrawals_root: Option<B256>,
+    /// The blob gas used by the block, from Cancun.
+    pub blob_gas_used: Option<u64>,
+    /// The excess blob gas of the block, from Cancun.
+    pub excess_blob_gas: Option<u64>,
+    /// The root of the parent beacon block, from Cancun.
+    pub parent_beacon_block_root: Option<B256>,
+    /// The hash of the execution layer requests of the block, from Prague and Isthmus.
+    pub requests_hash: Option<B256>,
+}
+
+impl BlockHeader {
+    /// Decodes a block header from its complete RLP encoding, rejecting trailing bytes.
+    pub fn decode_exact(mut data: &[u8]) -> Result<Self> {
+        let header = Self::decode(&mut data).map_err(|e| eyre!("Invalid block header: {}", e))?;
+        if !data.is_empty() {
+            return Err(eyre!(
+                "Unexpected {} trailing bytes in block header",
+                data.len()
+            ));
+        }
+        Ok(header)
+    }
+
+    /// Returns the hash of the block header.
+    pub fn hash_slow(&self) -> B256 {
+        keccak256(alloy_rlp::encode(self))
+    }
+
+    /// Returns the length of the RLP list payload of the header.
+    fn payload_length(&self) -> usize {
+        self.parent_hash.length()
+            + self.ommers_hash.length()
+            + self.beneficiary.length()
+            + self.state_root.length()
+            + self.transactions_root.length()
+            + self.receipts_root.length()
+            + self.logs_bloom.length()
+            + self.difficulty.length()
+            + self.number.length()
+            + self.gas_limit.length()
+            + self.gas_used.length()
+            + self.timestamp.length()
+            + self.extra_data.length()
+            + self.mix_hash.length()
+            + self.nonce.length()
+            + self.base_fee_per_gas.map_or(0, |v| v.length())
+            + self.withdrawals_root.map_or(0, |v| v.length())
+            + self.blob_gas_used.map_or(0, |v| v.length())
+            + self.excess_blob_gas.map_or(0, |v| v.length())
+            + self.parent_beacon_block_root.map_or(0, |v| v.length())
+            + self.requests_hash.map_or(0, |v| v.length())
+    }
+}
+
+impl Encodable for BlockHeader {
+    fn encode(&self, out: &mut dyn BufMut) {
+        Header {
+            list: true,
+            payload_length: self.payload_length(),
+        }
+        .encode(out);
+        self.parent_hash.encode(out);
+        self.ommers_hash.encode(out);
+        self.beneficiary.encode(out);
+        self.state_root.encode(out);
+        self.transactions_root.encode(out);
+        self.receipts_root.encode(out);
+        self.logs_bloom.encode(out);
+        self.difficulty.encode(out);
+        self.number.encode(out);
+        self.gas_limit.encode(out);
+        self.gas_used.encode(out);
+        self.timestamp.encode(out);
+        self.extra_data.encode(out);
+        self.mix_hash.encode(out);
+        self.nonce.encode(out);
+        if let Some(base_fee_per_gas) = self.base_fee_per_gas {
+            base_fee_per_gas.encode(out);
+        }
+        if let Some(withdrawals_root) = self.withdrawals_root {
+            withdrawals_root.encode(out);
+        }
+        if let Some(blob_gas_used) = self.blob_gas_used {
+            blob_gas_used.encode(out);
+        }
+        if let Some(excess_blob_gas) = self.excess_blob_gas {
+            excess_blob_gas.encode(out);
+        }
+        if let Some(parent_beacon_block_root) = self.parent_beacon_block_root {
+            parent_beacon_block_root.encode(out);
+        }
+        if let Some(requests_hash) = self.requests_hash {
+            requests_hash.encode(out);
+        }
+    }
+
+    fn length(&self) -> usize {
+        let payload_length = self.payload_length();
+        payload_length + alloy_rlp::length_of_length(payload_length)
+    }
+}
+
+impl Decodable for BlockHeader {
+    fn decode(buf: &mut &[u8]) -> alloy_rlp::Result<Self> {
+        let header = Header::decode(buf)?;
+        if !header.list {
+            return Err(alloy_rlp::Error::UnexpectedString);
+        }
+        if buf.len() < header.payload_length {
+            return Err(alloy_rlp::Error::InputTooShort);
+        }
+        let (mut payload, rest) = buf.split_at(header.payload_length);
+        *buf = rest;
+
+        let mut block_header = Self {
+            parent_hash: Decodable::decode(&mut payload)?,
+            ommers_hash: Decodable::decode(&mut payload)?,
+            beneficiary: Decodable::decode(&mut payload)?,
+            state_root: Decodable::decode(&mut payload)?,
+            transactions_root: Decodable::decode(&mut payload)?,
+            receipts_root: Decodable::decode(&mut payload)?,
+            logs_bloom: Decodable::decode(&mut payload)?,
+            difficulty: Decodable::decode(&mut payload)?,
+            number: Decodable::decode(&mut payload)?,
+            gas_limit: Decodable::decode(&mut payload)?,
+            gas_used: Decodable::decode(&mut payload)?,
+            timestamp: Decodable::decode(&mut payload)?,
+            extra_data: Decodable::decode(&mut payload)?,
+            mix_hash: Decodable::decode(&mut payload)?,
+            nonce: Decodable::decode(&mut payload)?,
+            ..Default::default()
+        };
+
+        // The fields introduced by later hardforks are appended to the header in order.
+        if !payload.is_empty() {
+            block_header.base_fee_per_gas = Some(Decodable::decode(&mut payload)?);
+        }
+        if !payload.is_empty() {
+            block_header.withdrawals_root = Some(Decodable::decode(&mut payload)?);
+        }
+        if !payload.is_empty() {
+            block_header.blob_gas_used = Some(Decodable::decode(&mut payload)?);
+        }
+        if !payload.is_empty() {
+            block_header.excess_blob_gas = Some(Decodable::decode(&mut payload)?);
+        }
+        if !payload.is_empty() {
+            block_header.parent_beacon_block_root = Some(Decodable::decode(&mut payload)?);
+        }
+        if !payload.is_empty() {
+            block_header.requests_hash = Some(Decodable::decode(&mut payload)?);
+        }
+        if !payload.is_empty() {
+            return Err(alloy_rlp::Error::ListLengthMismatch {
+                expected: header.payload_length - payload.len(),
+                got: header.payload_length,
+            });
+        }
+
+        Ok(block_header)
+    }
+}
+
+/// A state trie account.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TrieAccount {
+    /// The nonce of the account.
+    pub nonce: u64,
+    /// The balance of the account.
+    pub balance: U256,
+    /// The root of the account's storage trie.
+    pub storage_root: B256,
+    /// The hash of the account's code.
+    pub code_hash: B256,
+}
+
+impl Decodable for TrieAccount {
+    fn decode(buf: &mut &[u8]) -> alloy_rlp::Result<Self> {
+        let mut items = rlp_list(buf)?;
+        let account = Self {
+            nonce: Decodable::decode(&mut items)?,
+            balance: Decodable::decode(&mut items)?,
+            storage_root: Decodable::decode(&mut items)?,
+            code_hash: Decodable::decode(&mut items)?,
+        };
+        if !items.is_empty() {
+            return Err(alloy_rlp::Error::Custom(
+                "Unexpected trailing account fields",
+            ));
+        }
+        Ok(account)
+    }
+}
+
+impl TrieAccount {
+    /// Returns the length of the RLP list payload of the account.
+    fn payload_length(&self) -> usize {
+        self.nonce.length()
+            + self.balance.length()
+            + self.storage_root.length()
+            + self.code_hash.length()
+    }
+}
+
+impl Encodable for TrieAccount {
+    fn encode(&self, out: &mut dyn BufMut) {
+        Header {
+            list: true,
+            payload_length: self.payload_length(),
+        }
+        .encode(out);
+        self.nonce.encode(out);
+        self.balance.encode(out);
+        self.storage_root.encode(out);
+        self.code_hash.encode(out);
+    }
+
+    fn length(&self) -> usize {
+        let payload_length = self.payload_length();
+        payload_length + alloy_rlp::length_of_length(payload_length)
+    }
+}
+
+/// Returns the account at the given address in the state trie with the given root, using the
+/// trie nodes in the witness data. Returns [None] if the account does not exist, and an error
+/// if a trie node on the path to the account is missing from the witness data.
+pub fn trie_account(
+    witness_data: &BTreeMap<B256, Bytes>,
+    state_root: B256,
+    address: Address,
+) -> Result<Option<TrieAccount>> {
+    trie_get(witness_data, state_root, keccak256(address).as_slice())?
+        .map(|value| {
+            TrieAccount::decode(&mut value.as_ref())
+                .map_err(|e| eyre!("Invalid account {}: {}", address, e))
+        })
+        .transpose()
+}
+
+/// Returns the value stored under the given key in the Merkle Patricia Trie with the given
+/// root, using the trie nodes in the witness data. Returns [None] if the key does not exist,
+/// and an error if a trie node on the path to the key is missing from the witness data.
+pub fn trie_get(
+    witness_data: &BTreeMap<B256, Bytes>,
+    root: B256,
+    key: &[u8],
+) -> Result<Option<Bytes>> {
+    if root == EMPTY_ROOT_HASH {
+        return Ok(None);
+    }
+
+    let nibbles = to_nibbles(key);
+    let mut path = nibbles.as_slice();
+    let mut node = keccak_preimage(witness_data, root)
+        .ok_or_else(|| eyre!("Missing trie node {}", root))?
+        .clone();
+
+    loop {
+        let items = rlp_items(&node)?;
+        let next = match items.len() {
+            17 => {
+                let Some((&nibble, rest)) = path.split_first() else {
+                    let value = rlp_string(items[16])?;
+                    return Ok((!value.is_empty()).then(|| Bytes::copy_from_slice(value)));
+                };
+                path = rest;
+                items[nibble as usize]
+            }
+            2 => {
+                let encoded_path = rlp_string(items[0])?;
+                let Some(&flag) = encoded_path.first() else {
+                    return Err(eyre!("Empty trie node path"));
+                };
+                let mut node_path = to_nibbles(&encoded_path[1..]);
+                if flag & 0x10 != 0 {
+                    node_path.insert(0, flag & 0x0f);
+                }
+
+                if flag & 0x20 != 0 {
+                    // Leaf node.
+                    if path != node_path.as_slice() {
+                        return Ok(None);
+                    }
+                    return Ok(Some(Bytes::copy_from_slice(rlp_string(items[1])?)));
+                }
+
+                // Extension node.
+                let Some(rest) = path.strip_prefix(node_path.as_slice()) else {
+                    return Ok(None);
+                };
+                path = rest;
+                items[1]
+            }
+            n => return Err(eyre!("Invalid trie node with {} items", n)),
+        };
+
+        node = if next
+            .first()
+            .is_some_and(|&b| b >= alloy_rlp::EMPTY_LIST_CODE)
+        {
+            // Nodes shorter than 32 bytes are embedded in their parent.
+            Bytes::copy_from_slice(next)
+        } else {
+            let child = rlp_string(next)?;
+            if child.is_empty() {
+                return Ok(None);
+            }
+            if child.len() != 32 {
+                return Err(eyre!(
+                    "Invalid trie node reference of {} bytes",
+                    child.len()
+                ));
+            }
+            let hash = B256::from_slice(child);
+            keccak_preimage(witness_data, hash)
+                .ok_or_else(|| eyre!("Missing trie node {}", hash))?
+                .clone()
+        };
+    }
+}
+
+/// Returns the values of the ordered trie with the given root, such as the transactions or
+/// receipts trie of a block, whose keys are the RLP encoded indices of the values. Returns an
+/// error if a trie node is missing from the witness data.
+pub fn trie_list(witness_data: &BTreeMap<B256, Bytes>, root: B256) -> Result<Vec<Bytes>> {
+    let mut values = Vec::new();
+    while let Some(value) = trie_get(witness_data, root, &alloy_rlp::encode(values.len()))? {
+        values.push(value);
+    }
+    Ok(values)
+}
+
+/// Builds the ordered trie of the given values, such as the transactions or receipts trie of
+/// a block, keyed by the RLP encoded indices of the values. Every trie node referenced by hash
+/// is inserted into the witness data as a keccak256 preimage. Returns the root of the trie.
+pub fn ordered_trie(values: &[Bytes], witness_data: &mut BTreeMap<B256, Bytes>) -> B256 {
+    if values.is_empty() {
+        return EMPTY_ROOT_HASH;
+    }
+
+    let mut entries: Vec<(Vec<u8>, &[u8])> = values
+        .iter()
+        .enumerate()
+        .map(|(index, value)| (to_nibbles(&alloy_rlp::encode(index)), value.as_ref()))
+        .collect();
+    entries.sort();
+
+    // The root node is always referenced by hash, even if it is shorter than 32 bytes.
+    let root = trie_node(&entries, 0, witness_data);
+    let hash = keccak256(&root);
+    witness_data.insert(keccak_key(hash), Bytes::from(root));
+    hash
+}
+
+/// Encodes the trie node containing the given entries, sorted by their nibble paths, which
+/// share the first `depth` nibbles.
+fn trie_node(
+    entries: &[(Vec<u8>, &[u8])],
+    depth: usize,
+    witness_data: &mut BTreeMap<B256, Bytes>,
+) -> Vec<u8> {
+    if let [(path, value)] = entries {
+        return encode_list(&[
+            alloy_rlp::encode(hex_prefix(&path[depth..], true).as_slice()),
+            alloy_rlp::encode(*value),
+        ]);
+    }
+
+    // The entries are sorted, so the prefix shared by the first and last entries is shared by
+    // all of them.
+    let first = &entries[0].0;
+    let last = &entries[entries.len() - 1].0;
+    let shared = first[depth..]
+        .iter()
+        .zip(&last[depth..])
+        .take_while(|(a, b)| a == b)
+        .count();
+    if shared > 0 {
+        let child = trie_node(entries, depth + shared, witness_data);
+        return encode_list(&[
+            alloy_rlp::encode(hex_prefix(&first[depth..depth + shared], false).as_slice()),
+            node_reference(child, witness_data),
+        ]);
+    }
+
+    let mut rest = entries;
+    let mut value: &[u8] = &[];
+    if rest[0].0.len() == depth {
+        value = rest[0].1;
+        rest = &rest[1..];
+    }
+    let mut items = Vec::with_capacity(17);
+    for nibble in 0..16 {
+        let count = rest
+            .iter()
+            .take_while(|(path, _)| path[depth] == nibble)
+            .count();
+        let (children, tail) = rest.split_at(count);
+        rest = tail;
+        items.push(if children.is_empty() {
+            vec![alloy_rlp::EMPTY_STRING_CODE]
+        } else {
+            let child = trie_node(children, depth + 1, witness_data);
+            node_reference(child, witness_data)
+        });
+    }
+    items.push(alloy_rlp::encode(value));
+    encode_list(&items)
+}
+
+/// Returns the encoded reference to a child trie node. Nodes shorter than 32 bytes are
+/// embedded in their parent, longer nodes are referenced by hash.
+fn node_reference(node: Vec<u8>, witness_data: &mut BTreeMap<B256, Bytes>) -> Vec<u8> {
+    if node.len() < 32 {
+        return node;
+    }
+    let hash = keccak256(&node);
+    witness_data.insert(keccak_key(hash), Bytes::from(node));
+    alloy_rlp::encode(hash)
+}
+
+/// Returns the nibbles of the given bytes.
+fn to_nibbles(data: &[u8]) -> Vec<u8> {
+    data.iter().flat_map(|b| [b >> 4, b & 0x0f]).collect()
+}
+
+/// Returns the hex prefix encoding of a trie node path.
+fn hex_prefix(nibbles: &[u8], leaf: bool) -> Vec<u8> {
+    let flag = if leaf { 0x20 } else { 0x00 };
+    let mut out = Vec::with_capacity(nibbles.len() / 2 + 1);
+    let rest = if nibbles.len() % 2 == 1 {
+        out.push(flag | 0x10 | nibbles[0]);
+        &nibbles[1..]
+    } else {
+        out.push(flag);
+        nibbles
+    };
+    out.extend(rest.chunks(2).map(|pair| pair[0] << 4 | pair[1]));
+    out
+}
+
+/// Encodes the given RLP encoded items as an RLP list.
+pub fn encode_list(items: &[Vec<u8>]) -> Vec<u8> {
+    let payload_length = items.iter().map(Vec::len).sum();
+    let mut out = Vec::with_capacity(payload_length + alloy_rlp::length_of_length(payload_length));
+    Header {
+        list: true,
+        payload_length,
+    }
+    .encode(&mut out);
+    for item in items {
+        out.extend_from_slice(item);
+    }
+    out
+}
+
+/// Returns the payload of the RLP list at the front of the buffer, advancing past it.
+fn rlp_list<'a>(buf: &mut &'a [u8]) -> alloy_rlp::Result<&'a [u8]> {
+    Header::decode_bytes(buf, true)
+}
+
+/// Returns the raw RLP encodings of the items of the given RLP list.
+pub fn rlp_items(data: &[u8]) -> Result<Vec<&[u8]>> {
+    let mut buf = data;
+    let mut payload = rlp_list(&mut buf).map_err(|e| eyre!("Invalid RLP list: {}", e))?;
+    let mut items = Vec::new();
+    while !payload.is_empty() {
+        let start = payload;
+        let header = Header::decode(&mut payload).map_err(|e| eyre!("Invalid RLP item: {}", e))?;
+        if payload.len() < header.payload_length {
+            return Err(eyre!("Invalid RLP list: item exceeds list"));
+        }
+        payload = &payload[header.payload_length..];
+        items.push(&start[..start.len() - payload.len()]);
+    }
+    Ok(items)
+}
+
+/// Returns the payload of the given RLP string item.
+pub fn rlp_string(mut item: &[u8]) -> Result<&[u8]> {
+    Header::decode_bytes(&mut item, false).map_err(|e| eyre!("Invalid RLP string: {}", e))
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use alloy_primitives::{b256, hex};
+
+    /// Returns a trie containing only the given key and value, as its root and trie nodes.
+    pub(crate) fn single_leaf_trie(key: &[u8], value: &[u8]) -> (B256, BTreeMap<B256, Bytes>) {
+        // A trie with a single leaf stores the full key path, with the even leaf flag.
+        let mut path = vec![0x20];
+        path.extend_from_slice(key);
+        let leaf = encode_list(&[alloy_rlp::encode(path.as_slice()), alloy_rlp::encode(value)]);
+
+        let root = keccak256(&leaf);
+        let mut witness_data = BTreeMap::new();
+        witness_data.insert(keccak_key(root), Bytes::from(leaf));
+        (root, witness_data)
+    }
+
+    /// Returns a state trie containing only the given account, as its root and trie nodes.
+    pub(crate) fn single_account_trie(
+        address: Address,
+        account: &TrieAccount,
+    ) -> (B256, BTreeMap<B256, Bytes>) {
+        single_leaf_trie(keccak256(address).as_slice(), &alloy_rlp::encode(account))
+    }
+
+    #[test]
+    fn test_trie_account_single_leaf() {
+        let account = TrieAccount {
+            nonce: 1,
+            balance: U256::from(2),
+            storage_root: B256::from([3; 32]),
+            code_hash: B256::from([4; 32]),
+        };
+        let (root, witness_data) = single_account_trie(MESSAGE_PASSER_ADDRESS, &account);
+
+        assert_eq!(
+            trie_account(&witness_data, root, MESSAGE_PASSER_ADDRESS).unwrap(),
+            Some(account)
+        );
+        assert_eq!(
+            trie_account(&witness_data, root, Address::ZERO).unwrap(),
+            None
+        );
+        assert!(trie_account(&witness_data, B256::from([5; 32]), Address::ZERO).is_err());
+    }
+
+    #[test]
+    fn test_ordered_trie_round_trip() {
+        for count in [0, 1, 2, 16, 17, 130, 300] {
+            let values: Vec<Bytes> = (0..count)
+                .map(|i| Bytes::from(vec![i as u8; i % 70 + 1]))
+                .collect();
+            let mut witness_data = BTreeMap::new();
+            let root = ordered_trie(&values, &mut witness_data);
+            assert_eq!(trie_list(&witness_data, root).unwrap(), values);
+        }
+
+        // A single value trie matches the single leaf trie of the RLP encoded index 0.
+        let value = Bytes::from(vec![1; 40]);
+        let mut witness_data = BTreeMap::new();
+        let root = ordered_trie(std::slice::from_ref(&value), &mut witness_data);
+        assert_eq!(root, single_leaf_trie(&[0x80], &value).0);
+    }
+
+    #[test]
+    fn test_block_header_round_trip() {
+        let header = BlockHeader {
+            state_root: B256::from([4; 32]),
+            number: 1337,
+            gas_limit: 30_000_000,
+            timestamp: 1_700_000_000,
+            base_fee_per_gas: Some(7),
+            ..Default::default()
+        };
+        let encoded = alloy_rlp::encode(&header);
+        assert_eq!(encoded.len(), header.length());
+        assert_eq!(BlockHeader::decode_exact(&encoded).unwrap(), header);
+
+        let mut trailing = encoded.clone();
+        trailing.push(0);
+        assert!(BlockHeader::decode_exact(&trailing).is_err());
+        assert!(BlockHeader::decode_exact(&encoded[..encoded.len() - 1]).is_err());
+    }
+
+    #[test]
+    fn test_block_header_hash() {
+        // The Ethereum mainnet genesis block.
+        let genesis = BlockHeader {
+            ommers_hash: b256!("1dcc4de8dec75d7aab85b567b6ccd41ad312451b948a7413f0a142fd40d49347"),
+            state_root: b256!("d7f8974fb5ac78d9ac099b9ad5018bedc2ce0a72dad1827a1709da30580f0544"),
+            transactions_root: EMPTY_ROOT_HASH,
+            receipts_root: EMPTY_ROOT_HASH,
+            difficulty: U256::from(0x400000000u64),
+            gas_limit: 5000,
+            extra_data: Bytes::from_static(&hex!(
+                "11bbe8db4e347b4e8c937c1c8370e4b5ed33adb3db69cbdb7a38e1e50b1b82fa"
+            )),
+            nonce: B64::from(0x42u64),
+            ..Default::default()
+        };
+        assert_eq!(
+            genesis.hash_slow(),
+            b256!("d4e56740f876aef8c010b86a40d5f56745a118d0906a34e69aec8c0db1cb8fa3")
+        );
+
+        // A Prague header, with every optional field up to the requests hash of a block
+        // without requests.
+        let prague = BlockHeader {
+            base_fee_per_gas: Some(7),
+            withdrawals_root: Some(EMPTY_ROOT_HASH),
+            blob_gas_used: Some(0),
+            excess_blob_gas: Some(0),
+            parent_beacon_block_root: Some(B256::from([5; 32])),
+            requests_hash: Some(b256!(
+                "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+            )),
+            ..genesis.clone()
+        };
+        let encoded = alloy_rlp::encode(&prague);
+        assert_eq!(BlockHeader::decode_exact(&encoded).unwrap(), prague);
+        assert_eq!(
+            encoded.len(),
+            alloy_rlp::encode(&genesis).len() + 1 + 33 + 1 + 1 + 33 + 33
+        );
+    }
+
+    #[test]
+    fn test_output_root_preimage() {
+        let state_root = B256::from([1; 32]);
+        let storage_root = B256::from([2; 32]);
+        let block_hash = B256::from([3; 32]);
+
+        let mut data = vec![0u8; 32];
+        data.extend_from_slice(state_root.as_slice());
+        data.extend_from_slice(storage_root.as_slice());
+        data.extend_from_slice(block_hash.as_slice());
+        assert_eq!(
+            keccak256(&data),
+            output_root(state_root, storage_root, block_hash)
+        );
+        assert_eq!(
+            OutputRootPreimage::decode(&data).unwrap(),
+            OutputRootPreimage {
+                state_root,
+                storage_root,
+                block_hash
+            }
+        );
+    }
+}
@@ -0,0 +1,129 @@
+//! Data directories for the inputs and outputs of program runs.
+
+use clap::Parser;
+use color_eyre::Result;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tracing::{info, warn};
+
+/// The logging target to use for [tracing].
+const TARGET: &str = "workspace";
+
+/// CLI arguments for the data directories of program runs.
+#[derive(Parser, Clone, Debug, Default)]
+pub struct WorkspaceArgs {
+    /// Keep the data directories of successful runs
+    #[clap(
+        long,
+        help = "Keep the data directories of successful runs. Directories of failed runs are always kept"
+    )]
+    pub keep_data_dir: bool,
+    /// Optional directory to create the data directories in
+    #[clap(
+        long,
+        help = "Directory to create the data directories in, instead of the system temp directory"
+    )]
+    pub data_dir: Option<PathBuf>,
+}
+
+impl WorkspaceArgs {
+    /// Creates a new, uniquely named data directory for the given subcommand.
+    pub fn create(&self, name: &str) -> Result<DataDir> {
+        let root = self.data_dir.clone().unwrap_or_else(std::env::temp_dir);
+        Ok(DataDir {
+            path: create_unique_dir(&root.join(name))?,
+            keep: self.keep_data_dir,
+        })
+    }
+}
+
+/// A data directory for a single program run, removed once the run succeeds unless it is
+/// kept for debugging.
+#[derive(Debug)]
+pub struct DataDir {
+    /// The path to the directory.
+    path: PathBuf,
+    /// Whether to keep the directory after a successful run.
+    keep: bool,
+}
+
+impl DataDir {
+    /// Returns the path to the directory.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Finishes the run using the directory, removing the directory if the run succeeded and
+    /// it is not kept.
+    pub fn finish(self, success: bool) {
+        if !success {
+            warn!(
+                target: TARGET,
+                "Keeping data directory of failed run {:?}", self.path
+            );
+        } else if self.keep {
+            info!(target: TARGET, "Keeping data directory {:?}", self.path);
+        } else if let Err(e) = std::fs::remove_dir_all(&self.path) {
+            warn!(target: TARGET, "Failed to remove {:?}: {}", self.path, e);
+        }
+    }
+}
+
+/// Creates a new, uniquely named directory under the given parent directory.
+///
+/// Directory names combine the current time, the process id and a process-wide counter, so
+/// concurrent runs never share a directory.
+fn create_unique_dir(parent: &Path) -> Result<PathBuf> {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+
+    let dirname = format!(
+        "{}-{}-{}",
+        SystemTime::now().duration_since(UNIX_EPOCH)?.as_millis(),
+        std::process::id(),
+        COUNTER.fetch_add(1, Ordering::Relaxed)
+    );
+    std::fs::create_dir_all(parent)?;
+
+    // `create_dir` fails if the directory already exists, which guarantees exclusive use.
+    let dir = parent.join(dirname);
+    std::fs::create_dir(&dir)?;
+    Ok(dir)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_data_dir_cleanup() {
+        let root = std::env::temp_dir().join(format!("opfp-workspace-test-{}", std::process::id()));
+        let workspace = WorkspaceArgs {
+            keep_data_dir: false,
+            data_dir: Some(root.clone()),
+        };
+
+        let succeeded = workspace.create("test").expect("failed to create data dir");
+        let failed = workspace.create("test").expect("failed to create data dir");
+        assert_ne!(succeeded.path(), failed.path());
+        assert!(succeeded.path().starts_with(root.join("test")));
+
+        let (succeeded_path, failed_path) = (succeeded.path().to_owned(), failed.path().to_owned());
+        succeeded.finish(true);
+        failed.finish(false);
+        assert!(!succeeded_path.exists());
+        assert!(failed_path.exists());
+
+        let kept = WorkspaceArgs {
+            keep_data_dir: true,
+            ..workspace
+        }
+        .create("test")
+        .expect("failed to create data dir");
+        let kept_path = kept.path().to_owned();
+        kept.finish(true);
+        assert!(kept_path.exists());
+
+        std::fs::remove_dir_all(root).expect("failed to remove test dir");
+    }
+}
@@ -0,0 +1,344 @@
+//! Module containing the file encodings of the fault proof fixture.
+
+use std::collections::BTreeMap;
+use std::io::{Read, Write};
+use std::path::Path;
+
+use alloy_primitives::{Bytes, B256};
+use color_eyre::{eyre::eyre, Result};
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::{migrate, FaultProofFixture, FaultProofInputs, FaultProofStatus};
+
+/// The magic bytes at the start of a gzip stream.
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+/// The magic bytes at the start of a zstd frame.
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+/// The magic bytes at the start of a binary fixture container.
+const BINARY_MAGIC: [u8; 4] = *b"FPFX";
+
+/// The serialization format of a fixture file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FixtureFormat {
+    /// The fixture is encoded as JSON.
+    Json,
+    /// The fixture is encoded as a binary container.
+    ///
+    /// The container starts with the `FPFX` magic bytes, followed by the length-prefixed JSON
+    /// encoding of the fixture without its witness data, the number of witness entries and
+    /// one record per witness entry. Each record contains the 32 byte key followed by the
+    /// length-prefixed value. All lengths are big-endian `u32`s, the entry count is a
+    /// big-endian `u64`.
+    Binary,
+}
+
+/// The compression applied to a fixture file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FixtureCompression {
+    /// The fixture is not compressed.
+    None,
+    /// The fixture is gzip compressed.
+    Gzip,
+    /// The fixture is zstd compressed.
+    Zstd,
+}
+
+/// The encoding of a fixture file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FixtureEncoding {
+    /// The serialization format of the fixture.
+    pub format: FixtureFormat,
+    /// The compression applied to the serialized fixture.
+    pub compression: FixtureCompression,
+}
+
+impl FixtureEncoding {
+    /// Returns the encoding for a fixture file from its extensions, e.g. `.json`, `.json.gz`,
+    /// `.json.zst`, `.bin` or `.bin.gz`. Unknown extensions are treated as uncompressed JSON.
+    pub fn from_path(path: &Path) -> Self {
+        let name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+
+        let (name, compression) = if let Some(name) = name.strip_suffix(".gz") {
+            (name, FixtureCompression::Gzip)
+        } else if let Some(name) = name.strip_suffix(".zst") {
+            (name, FixtureCompression::Zstd)
+        } else {
+            (name.as_str(), FixtureCompression::None)
+        };
+        let format = if name.ends_with(".bin") {
+            FixtureFormat::Binary
+        } else {
+            FixtureFormat::Json
+        };
+
+        Self {
+            format,
+            compression,
+        }
+    }
+
+    /// Returns whether the path has the extension of a known fixture encoding.
+    pub fn is_fixture_path(path: &Path) -> bool {
+        let name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+        [
+            ".json",
+            ".json.gz",
+            ".json.zst",
+            ".bin",
+            ".bin.gz",
+            ".bin.zst",
+        ]
+        .iter()
+        .any(|ext| name.ends_with(ext))
+    }
+}
+
+/// The fixture without its witness data, as stored in the header of the binary container.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct BinaryHeader<'a> {
+    version: u64,
+    inputs: &'a FaultProofInputs,
+    expected_status: FaultProofStatus,
+}
+
+impl FaultProofFixture {
+    /// Reads the fixture at the given path, detecting its encoding from its contents.
+    pub fn load(path: &Path) -> Result<Self> {
+        let data = std::fs::read(path)?;
+        Ok(Self::decode(&data)?.0)
+    }
+
+    /// Writes the fixture to the given path, using the encoding of its file extension.
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let data = self.encode(FixtureEncoding::from_path(path))?;
+        std::fs::write(path, data)?;
+        Ok(())
+    }
+
+    /// Decodes a fixture in any supported encoding, detecting the encoding from the magic
+    /// bytes of the data. Fixtures of older format versions are upgraded to the current
+    /// version.
+    ///
+    /// Returns the fixture along with the format version it was decoded from.
+    pub fn decode(data: &[u8]) -> Result<(Self, u64)> {
+        if data.starts_with(&GZIP_MAGIC) {
+            let mut decompressed = Vec::new();
+            GzDecoder::new(data).read_to_end(&mut decompressed)?;
+            return Self::decode(&decompressed);
+        }
+        if data.starts_with(&ZSTD_MAGIC) {
+            return Self::decode(&zstd::decode_all(data)?);
+        }
+        if data.starts_with(&BINARY_MAGIC) {
+            return decode_binary(&data[BINARY_MAGIC.len()..]);
+        }
+
+        let mut fixture: Value = serde_json::from_slice(data)?;
+        let version = migrate(&mut fixture)?;
+        Ok((serde_json::from_value(fixture)?, version))
+    }
+
+    /// Encodes the fixture with the given encoding.
+    pub fn encode(&self, encoding: FixtureEncoding) -> Result<Vec<u8>> {
+        let data = match encoding.format {
+            FixtureFormat::Json => serde_json::to_vec_pretty(self)?,
+            FixtureFormat::Binary => self.encode_binary()?,
+        };
+
+        match encoding.compression {
+            FixtureCompression::None => Ok(data),
+            FixtureCompression::Gzip => {
+                let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+                encoder.write_all(&data)?;
+                Ok(encoder.finish()?)
+            }
+            FixtureCompression::Zstd => Ok(zstd::encode_all(data.as_slice(), 0)?),
+        }
+    }
+
+    /// Encodes the fixture as a binary container.
+    fn encode_binary(&self) -> Result<Vec<u8>> {
+        let header = serde_json::to_vec(&BinaryHeader {
+            version: self.version,
+            inputs: &self.inputs,
+            expected_status: self.expected_status,
+        })?;
+
+        let witness_size: usize = self
+            .witness_data
+            .values()
+            .map(|value| B256::len_bytes() + 4 + value.len())
+            .sum();
+        let mut out = Vec::with_capacity(BINARY_MAGIC.len() + 4 + header.len() + 8 + witness_size);
+        out.extend_from_slice(&BINARY_MAGIC);
+        out.extend_from_slice(&encode_len(header.len())?);
+        out.extend_from_slice(&header);
+        out.extend_from_slice(&(self.witness_data.len() as u64).to_be_bytes());
+        for (key, value) in &self.witness_data {
+            out.extend_from_slice(key.as_slice());
+            out.extend_from_slice(&encode_len(value.len())?);
+            out.extend_from_slice(value);
+        }
+
+        Ok(out)
+    }
+}
+
+/// Decodes the body of a binary fixture container, following the magic bytes.
+fn decode_binary(mut data: &[u8]) -> Result<(FaultProofFixture, u64)> {
+    let header_len = read_u32(&mut data)? as usize;
+    let header = read_bytes(&mut data, header_len)?;
+
+    let mut fixture: Value = serde_json::from_slice(header)?;
+    fixture
+        .as_object_mut()
+        .ok_or_else(|| eyre!("Fixture header is not a JSON object"))?
+        .insert("witnessData".to_string(), Value::Object(Default::default()));
+    let version = migrate(&mut fixture)?;
+    let mut fixture: FaultProofFixture = serde_json::from_value(fixture)?;
+
+    let count = u64::from_be_bytes(read_bytes(&mut data, 8)?.try_into()?);
+    let mut witness_data = BTreeMap::new();
+    for _ in 0..count {
+        let key = B256::from_slice(read_bytes(&mut data, B256::len_bytes())?);
+        let value_len = read_u32(&mut data)? as usize;
+        let value = Bytes::copy_from_slice(read_bytes(&mut data, value_len)?);
+        witness_data.insert(key, value);
+    }
+    if !data.is_empty() {
+        return Err(eyre!(
+            "Unexpected {} trailing bytes in binary fixture",
+            data.len()
+        ));
+    }
+    fixture.witness_data = witness_data;
+
+    Ok((fixture, version))
+}
+
+/// Encodes a length as a big-endian `u32`.
+fn encode_len(len: usize) -> Result<[u8; 4]> {
+    Ok(u32::try_from(len)
+        .map_err(|_| eyre!("Length {} exceeds the binary fixture limit", len))?
+        .to_be_bytes())
+}
+
+/// Reads a big-endian `u32` from the front of the data.
+fn read_u32(data: &mut &[u8]) -> Result<u32> {
+    Ok(u32::from_be_bytes(read_bytes(data, 4)?.try_into()?))
+}
+
+/// Reads the given number of bytes from the front of the data.
+fn read_bytes<'a>(data: &mut &'a [u8], len: usize) -> Result<&'a [u8]> {
+    if data.len() < len {
+        return Err(eyre!("Unexpected end of binary fixture"));
+    }
+    let (bytes, rest) = data.split_at(len);
+    *data = rest;
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::FIXTURE_VERSION;
+
+    fn test_fixture() -> FaultProofFixture {
+        let mut witness_data = BTreeMap::new();
+        witness_data.insert(B256::from([1; 32]), Bytes::from([1; 32]));
+        witness_data.insert(B256::from([2; 32]), Bytes::from(vec![2; 1000]));
+        witness_data.insert(B256::from([3; 32]), Bytes::new());
+
+        FaultProofFixture {
+            version: FIXTURE_VERSION,
+            inputs: FaultProofInputs {
+                l1_head: B256::from([1; 32]),
+                l2_head: B256::from([2; 32]),
+                l2_claim: B256::from([3; 32]),
+                l2_output_root: B256::from([4; 32]),
+                l2_block_number: 1337,
+                chain_definition: Default::default(),
+            },
+            expected_status: FaultProofStatus::Invalid,
+            witness_data,
+        }
+    }
+
+    #[test]
+    fn test_encoding_from_path() {
+        let cases = [
+            ("Writer.json", FixtureFormat::Json, FixtureCompression::None),
+            (
+                "Writer.json.gz",
+                FixtureFormat::Json,
+                FixtureCompression::Gzip,
+            ),
+            (
+                "Writer.json.zst",
+                FixtureFormat::Json,
+                FixtureCompression::Zstd,
+            ),
+            (
+                "Writer.bin",
+                FixtureFormat::Binary,
+                FixtureCompression::None,
+            ),
+            (
+                "Writer.bin.zst",
+                FixtureFormat::Binary,
+                FixtureCompression::Zstd,
+            ),
+        ];
+        for (path, format, compression) in cases {
+            assert_eq!(
+                FixtureEncoding::from_path(Path::new(path)),
+                FixtureEncoding {
+                    format,
+                    compression
+                }
+            );
+        }
+    }
+
+    #[test]
+    fn test_encoding_round_trip() {
+        let fixture = test_fixture();
+        for format in [FixtureFormat::Json, FixtureFormat::Binary] {
+            for compression in [
+                FixtureCompression::None,
+                FixtureCompression::Gzip,
+                FixtureCompression::Zstd,
+            ] {
+                let encoding = FixtureEncoding {
+                    format,
+                    compression,
+                };
+                let data = fixture.encode(encoding).expect("failed to encode fixture");
+                let (decoded, version) =
+                    FaultProofFixture::decode(&data).expect("failed to decode fixture");
+                assert_eq!(version, FIXTURE_VERSION);
+                assert_eq!(fixture, decoded, "round trip failed for {:?}", encoding);
+            }
+        }
+    }
+
+    #[test]
+    fn test_decode_truncated_binary_fixture() {
+        let data = test_fixture()
+            .encode(FixtureEncoding {
+                format: FixtureFormat::Binary,
+                compression: FixtureCompression::None,
+            })
+            .expect("failed to encode fixture");
+        assert!(FaultProofFixture::decode(&data[..data.len() - 1]).is_err());
+    }
+}
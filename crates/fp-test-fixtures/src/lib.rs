@@ -7,11 +7,22 @@ use superchain_primitives::RollupConfig;
 use serde::{Deserialize, Serialize};
 use serde_repr::{Deserialize_repr, Serialize_repr};
 
+mod encoding;
+pub use encoding::{FixtureCompression, FixtureEncoding, FixtureFormat};
+
+mod migrate;
+pub use migrate::{fixture_version, migrate, FIXTURE_VERSION};
+
+mod preimage;
+pub use preimage::{PreimageKey, PreimageKeyType, WitnessStats};
+
 /// The fault proof fixture is the top-level object that contains
 /// everything needed to run a fault proof test.
-#[derive(Serialize, Deserialize, Debug, Default, PartialEq, Eq)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
 #[serde(rename_all = "camelCase")]
 pub struct FaultProofFixture {
+    /// The version of the fixture format.
+    pub version: u64,
     /// The inputs to the fault proof test.
     pub inputs: FaultProofInputs,
     /// The expected status of the fault proof test.
@@ -20,8 +31,19 @@ pub struct FaultProofFixture {
     pub witness_data: BTreeMap<B256, Bytes>,
 }
 
+impl Default for FaultProofFixture {
+    fn default() -> Self {
+        Self {
+            version: FIXTURE_VERSION,
+            inputs: Default::default(),
+            expected_status: Default::default(),
+            witness_data: Default::default(),
+        }
+    }
+}
+
 /// The fault proof inputs are the inputs to the fault proof test.
-#[derive(Serialize, Deserialize, Debug, Default, PartialEq, Eq)]
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq, Eq)]
 #[serde(rename_all = "camelCase")]
 pub struct FaultProofInputs {
     /// The L1 head block hash.
@@ -39,7 +61,7 @@ pub struct FaultProofInputs {
 }
 
 /// The chain definition, either named or unnamed with a rollup config and genesis.
-#[derive(Serialize, Deserialize, Debug, PartialEq, Eq)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
 #[serde(rename_all = "camelCase")]
 pub enum ChainDefinition {
     /// Named chain definition.
@@ -55,7 +77,7 @@ impl Default for ChainDefinition {
 }
 
 /// The genesis block information.
-#[derive(Serialize, Deserialize, Debug, Default, PartialEq, Eq)]
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq, Eq)]
 #[serde(rename_all = "camelCase")]
 pub struct Genesis {
     /// The chain configuration.
@@ -98,7 +120,7 @@ pub struct Genesis {
 }
 
 /// The chain configuration.
-#[derive(Serialize, Deserialize, Debug, Default, PartialEq, Eq)]
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq, Eq)]
 #[serde(rename_all = "camelCase")]
 pub struct ChainConfig {
     /// The chain ID.
@@ -199,7 +221,7 @@ pub struct ChainConfig {
 }
 
 /// The optimism configuration.
-#[derive(Serialize, Deserialize, Debug, Default, PartialEq, Eq)]
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq, Eq)]
 #[serde(rename_all = "camelCase")]
 pub struct OptimismConfig {
     /// The eip1559 elasticity.
@@ -229,7 +251,7 @@ pub struct AccountState {
 }
 
 /// The fault proof status is the result of executing the fault proof program.
-#[derive(Serialize_repr, Deserialize_repr, Debug, Default, PartialEq, Eq)]
+#[derive(Serialize_repr, Deserialize_repr, Debug, Default, Clone, Copy, PartialEq, Eq)]
 #[repr(u8)]
 pub enum FaultProofStatus {
     /// The claim is valid.
@@ -312,6 +334,7 @@ mod tests {
         witness_data.insert(B256::from([2; 32]), Bytes::from([2; 32]));
 
         let fixture = FaultProofFixture {
+            version: FIXTURE_VERSION,
             inputs: FaultProofInputs {
                 l1_head: B256::from([1; 32]),
                 l2_head: B256::from([2; 32]),
@@ -330,4 +353,35 @@ mod tests {
             .expect("failed to deserialize fixture");
         assert_eq!(fixture, deserialized_fixture);
     }
+
+    #[test]
+    fn test_migrate_unversioned_fixture() {
+        let fixture = FaultProofFixture {
+            expected_status: FaultProofStatus::Invalid,
+            ..Default::default()
+        };
+
+        let mut legacy_fixture =
+            serde_json::to_value(&fixture).expect("failed to serialize fixture");
+        legacy_fixture
+            .as_object_mut()
+            .expect("fixture is not an object")
+            .remove("version");
+        assert_eq!(fixture_version(&legacy_fixture).unwrap(), 0);
+
+        let data = serde_json::to_vec(&legacy_fixture).expect("failed to serialize fixture");
+        let (migrated_fixture, version) =
+            FaultProofFixture::decode(&data).expect("failed to migrate fixture");
+        assert_eq!(version, 0);
+        assert_eq!(migrated_fixture.version, FIXTURE_VERSION);
+        assert_eq!(fixture, migrated_fixture);
+    }
+
+    #[test]
+    fn test_migrate_rejects_newer_fixture() {
+        let mut fixture = serde_json::to_value(FaultProofFixture::default())
+            .expect("failed to serialize fixture");
+        fixture["version"] = serde_json::Value::from(FIXTURE_VERSION + 1);
+        assert!(migrate(&mut fixture).is_err());
+    }
 }
@@ -0,0 +1,58 @@
+//! Module containing the fault proof fixture format versioning and migrations.
+
+use color_eyre::{eyre::eyre, Result};
+use serde_json::{Map, Value};
+
+/// The current version of the fault proof fixture format.
+pub const FIXTURE_VERSION: u64 = 1;
+
+/// A migration which upgrades a fixture from one format version to the next.
+type Migration = fn(&mut Map<String, Value>) -> Result<()>;
+
+/// The fixture migrations, indexed by the version they upgrade from.
+const MIGRATIONS: [Migration; FIXTURE_VERSION as usize] = [migrate_v0_to_v1];
+
+/// Returns the format version of a fixture in its JSON representation.
+///
+/// Fixtures without a `version` field predate format versioning and are treated as
+/// version 0.
+pub fn fixture_version(fixture: &Value) -> Result<u64> {
+    let fixture = fixture
+        .as_object()
+        .ok_or_else(|| eyre!("Fixture is not a JSON object"))?;
+    match fixture.get("version") {
+        None => Ok(0),
+        Some(version) => version
+            .as_u64()
+            .ok_or_else(|| eyre!("Invalid fixture version: {}", version)),
+    }
+}
+
+/// Upgrades a fixture in its JSON representation to the current format version, returning
+/// the version it was upgraded from.
+pub fn migrate(fixture: &mut Value) -> Result<u64> {
+    let original_version = fixture_version(fixture)?;
+    if original_version > FIXTURE_VERSION {
+        return Err(eyre!(
+            "Unsupported fixture version {}, the latest supported version is {}",
+            original_version,
+            FIXTURE_VERSION
+        ));
+    }
+
+    let fixture = fixture
+        .as_object_mut()
+        .ok_or_else(|| eyre!("Fixture is not a JSON object"))?;
+    for version in original_version..FIXTURE_VERSION {
+        MIGRATIONS[version as usize](fixture)
+            .map_err(|e| eyre!("Failed to migrate fixture from version {}: {}", version, e))?;
+        fixture.insert("version".to_string(), Value::from(version + 1));
+    }
+
+    Ok(original_version)
+}
+
+/// Version 0 fixtures predate the `version` field and otherwise share the version 1 layout.
+fn migrate_v0_to_v1(_fixture: &mut Map<String, Value>) -> Result<()> {
+    Ok(())
+}
@@ -0,0 +1,246 @@
+//! Module containing the typed preimage keys of the fixture witness data.
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+use alloy_primitives::{Bytes, B256};
+use serde::{Deserialize, Serialize};
+
+use crate::FaultProofFixture;
+
+/// The type of a preimage key, stored in the first byte of the key.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[serde(rename_all = "camelCase")]
+#[repr(u8)]
+pub enum PreimageKeyType {
+    /// A local key, bootstrapped by the host from the program inputs.
+    Local = 1,
+    /// A global key, derived from the keccak256 hash of the preimage.
+    Keccak256 = 2,
+    /// A global key for generic data, reserved for future use.
+    GlobalGeneric = 3,
+    /// A global key, derived from the sha256 hash of the preimage.
+    Sha256 = 4,
+    /// A global key for a blob field element, derived from the blob commitment and the
+    /// field element index.
+    Blob = 5,
+    /// A global key for the result of a precompile call, derived from the precompile address
+    /// and its input.
+    Precompile = 6,
+}
+
+impl PreimageKeyType {
+    /// All preimage key types, in the order of their type bytes.
+    pub const ALL: [PreimageKeyType; 6] = [
+        PreimageKeyType::Local,
+        PreimageKeyType::Keccak256,
+        PreimageKeyType::GlobalGeneric,
+        PreimageKeyType::Sha256,
+        PreimageKeyType::Blob,
+        PreimageKeyType::Precompile,
+    ];
+}
+
+impl TryFrom<u8> for PreimageKeyType {
+    type Error = String;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            1 => Ok(PreimageKeyType::Local),
+            2 => Ok(PreimageKeyType::Keccak256),
+            3 => Ok(PreimageKeyType::GlobalGeneric),
+            4 => Ok(PreimageKeyType::Sha256),
+            5 => Ok(PreimageKeyType::Blob),
+            6 => Ok(PreimageKeyType::Precompile),
+            _ => Err(format!("Unknown preimage key type {}", value)),
+        }
+    }
+}
+
+impl From<PreimageKeyType> for u8 {
+    fn from(key_type: PreimageKeyType) -> u8 {
+        key_type as u8
+    }
+}
+
+impl fmt::Display for PreimageKeyType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            PreimageKeyType::Local => "local",
+            PreimageKeyType::Keccak256 => "keccak256",
+            PreimageKeyType::GlobalGeneric => "global-generic",
+            PreimageKeyType::Sha256 => "sha256",
+            PreimageKeyType::Blob => "blob",
+            PreimageKeyType::Precompile => "precompile",
+        };
+        f.write_str(name)
+    }
+}
+
+/// A preimage key, made up of a key type byte followed by 31 bytes of key data.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct PreimageKey {
+    key_type: PreimageKeyType,
+    data: [u8; 31],
+}
+
+impl PreimageKey {
+    /// Creates a new [PreimageKey] of the given type from a 32 byte hash, replacing its first
+    /// byte with the key type.
+    pub fn new(hash: B256, key_type: PreimageKeyType) -> Self {
+        let mut data = [0u8; 31];
+        data.copy_from_slice(&hash[1..]);
+        Self { key_type, data }
+    }
+
+    /// Creates a new local [PreimageKey] for the given local index.
+    pub fn new_local(index: u64) -> Self {
+        let mut data = [0u8; 31];
+        data[23..].copy_from_slice(&index.to_be_bytes());
+        Self {
+            key_type: PreimageKeyType::Local,
+            data,
+        }
+    }
+
+    /// Returns the type of the preimage key.
+    pub fn key_type(&self) -> PreimageKeyType {
+        self.key_type
+    }
+
+    /// Returns the 31 bytes of key data following the key type.
+    pub fn data(&self) -> &[u8; 31] {
+        &self.data
+    }
+}
+
+impl From<PreimageKey> for B256 {
+    fn from(key: PreimageKey) -> B256 {
+        let mut out = B256::ZERO;
+        out[0] = key.key_type.into();
+        out[1..].copy_from_slice(&key.data);
+        out
+    }
+}
+
+impl TryFrom<B256> for PreimageKey {
+    type Error = String;
+
+    fn try_from(key: B256) -> Result<Self, Self::Error> {
+        Ok(Self::new(key, PreimageKeyType::try_from(key[0])?))
+    }
+}
+
+impl fmt::Display for PreimageKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", B256::from(*self))
+    }
+}
+
+/// The number and total size of the witnesses of a preimage key type.
+#[derive(Serialize, Deserialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct WitnessStats {
+    /// The number of witnesses.
+    pub count: usize,
+    /// The total size of the witness values in bytes.
+    pub size: usize,
+}
+
+impl FaultProofFixture {
+    /// Returns the witnesses with typed preimage keys. Witnesses whose keys have an unknown
+    /// type are skipped.
+    pub fn witnesses(&self) -> impl Iterator<Item = (PreimageKey, &Bytes)> {
+        self.witness_data
+            .iter()
+            .filter_map(|(key, value)| Some((PreimageKey::try_from(*key).ok()?, value)))
+    }
+
+    /// Returns the witnesses whose keys have the given preimage key type.
+    pub fn witnesses_of_type(
+        &self,
+        key_type: PreimageKeyType,
+    ) -> impl Iterator<Item = (PreimageKey, &Bytes)> {
+        self.witnesses()
+            .filter(move |(key, _)| key.key_type() == key_type)
+    }
+
+    /// Returns the keys of the witnesses whose keys have an unknown preimage key type.
+    pub fn unknown_witness_keys(&self) -> impl Iterator<Item = &B256> {
+        self.witness_data
+            .keys()
+            .filter(|key| PreimageKeyType::try_from(key[0]).is_err())
+    }
+
+    /// Returns the number and total size of the witnesses of each preimage key type present
+    /// in the witness data.
+    pub fn witness_stats(&self) -> BTreeMap<PreimageKeyType, WitnessStats> {
+        let mut stats = BTreeMap::<PreimageKeyType, WitnessStats>::new();
+        for (key, value) in self.witnesses() {
+            let entry = stats.entry(key.key_type()).or_default();
+            entry.count += 1;
+            entry.size += value.len();
+        }
+        stats
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_preimage_key_round_trip() {
+        for key_type in PreimageKeyType::ALL {
+            let raw = B256::from([u8::from(key_type); 32]);
+            let key = PreimageKey::try_from(raw).expect("failed to parse preimage key");
+            assert_eq!(key.key_type(), key_type);
+            assert_eq!(B256::from(key), raw);
+        }
+        assert!(PreimageKey::try_from(B256::ZERO).is_err());
+        assert!(PreimageKey::try_from(B256::from([7; 32])).is_err());
+
+        let local = B256::from(PreimageKey::new_local(4));
+        assert_eq!(local[0], 1);
+        assert_eq!(local[31], 4);
+        assert!(local[1..31].iter().all(|&b| b == 0));
+    }
+
+    #[test]
+    fn test_witness_stats() {
+        let mut fixture = FaultProofFixture::default();
+        fixture
+            .witness_data
+            .insert(B256::from([2; 32]), Bytes::from(vec![0; 10]));
+        fixture.witness_data.insert(
+            PreimageKey::new(B256::from([1; 32]), PreimageKeyType::Keccak256).into(),
+            Bytes::from(vec![0; 5]),
+        );
+        fixture
+            .witness_data
+            .insert(B256::from([5; 32]), Bytes::from(vec![0; 32]));
+        fixture
+            .witness_data
+            .insert(B256::from([9; 32]), Bytes::from(vec![0; 1]));
+
+        let stats = fixture.witness_stats();
+        assert_eq!(stats.len(), 2);
+        assert_eq!(
+            stats[&PreimageKeyType::Keccak256],
+            WitnessStats { count: 2, size: 15 }
+        );
+        assert_eq!(
+            stats[&PreimageKeyType::Blob],
+            WitnessStats { count: 1, size: 32 }
+        );
+        assert_eq!(
+            fixture
+                .witnesses_of_type(PreimageKeyType::Keccak256)
+                .count(),
+            2
+        );
+        assert_eq!(
+            fixture.unknown_witness_keys().collect::<Vec<_>>(),
+            vec![&B256::from([9; 32])]
+        );
+    }
+}